@@ -0,0 +1,49 @@
+use std::time::Instant;
+use particles::board::Board;
+
+const TICKS: usize = 20;
+
+fn main() {
+    let board = Board::new(
+        50_000,
+        5,
+        512.0,
+        512.0,
+        32,
+        32,
+        0.1,
+        0.04,
+        0.001,
+        0.001,
+        16.0,
+        true,
+        0,
+        0,
+        0,
+    );
+
+    let mut serial_board = board.clone();
+    let start = Instant::now();
+    for _ in 0..TICKS {
+        serial_board.simulate_serial();
+    }
+    let serial_time = start.elapsed();
+
+    let mut parallel_board = board;
+    let start = Instant::now();
+    for _ in 0..TICKS {
+        parallel_board.simulate_parallel();
+    }
+    let parallel_time = start.elapsed();
+
+    println!("{} particles, {} ticks", 50_000, TICKS);
+    println!("serial:   {:?} per tick", serial_time / TICKS as u32);
+    println!("parallel: {:?} per tick ({} threads)", parallel_time / TICKS as u32, rayon::current_num_threads());
+    println!("speedup:  {:.2}x", serial_time.as_secs_f64() / parallel_time.as_secs_f64());
+
+    // the same physics, only summed up in a different order (tests/parallel.rs compares the positions)
+    let particle_count = |board: &Board| board.bounding_rects.iter().map(|br| br.len()).sum::<usize>();
+    assert_eq!(particle_count(&serial_board), particle_count(&parallel_board));
+    let total_vel = |board: &Board| board.bounding_rects.iter().flatten().map(|p| p.vel.mag()).sum::<f64>();
+    println!("total speed: serial {:.6}, parallel {:.6}", total_vel(&serial_board), total_vel(&parallel_board));
+}
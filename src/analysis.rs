@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::board::Board;
+use crate::particle::Particle;
+use crate::vec2;
+use crate::vec2::Vec2;
+
+/// Measures of how much structure a board has formed.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Scores {
+    /// The number of clusters of at least the minimum size.
+    pub cluster_count: usize,
+    /// The mean number of particles in those clusters.
+    pub mean_cluster_size: f64,
+    /// How coherently clusters move at the moment, from 0 to 1: the magnitude of the summed velocities of a cluster's
+    /// particles divided by the sum of their speeds, averaged over clusters weighted by their total speed. High for
+    /// clusters whose particles move as one, whether or not they keep their direction over time.
+    pub motion_coherence: f64,
+    /// How well the particles of clusters keep their velocities over time, from -1 (reversed) to 1 (unchanged): the
+    /// summed dot products of each particle's velocity with its velocity in an earlier sample, divided by the summed
+    /// products of the speeds. Only particles that were already on the board at the sample count.
+    pub motion_persistence: f64,
+    /// How much more often neighbours share a type than in a random mix, from 0 (random) to 1 (fully segregated).
+    /// Negative when types mix more evenly than randomly.
+    pub type_segregation: f64,
+}
+
+/// The score a search ranks rules by.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub enum Score {
+    ClusterCount,
+    MeanClusterSize,
+    MotionCoherence,
+    MotionPersistence,
+    TypeSegregation,
+    /// Moving, segregated and large clusters, see `Scores::combined`.
+    Combined,
+}
+
+/// The velocity of every particle by id, taken some ticks before the board is measured.
+pub type VelocitySample = HashMap<u64, Vec2>;
+
+pub fn sample_velocities(board: &Board) -> VelocitySample {
+    board.bounding_rects.iter().flatten().map(|p| (p.id, p.vel)).collect()
+}
+
+/// Simulates `ticks` ticks and returns the velocities sampled `persistence_lag` ticks before the end (or at the start,
+/// for shorter runs), to measure motion persistence with.
+pub fn simulate_sampled(board: &mut Board, ticks: usize, persistence_lag: usize) -> VelocitySample {
+    let sample_tick = ticks.saturating_sub(persistence_lag);
+
+    let mut sample = None;
+    for tick in 0..ticks {
+        if tick == sample_tick {
+            sample = Some(sample_velocities(board));
+        }
+        board.simulate();
+    }
+
+    sample.unwrap_or_else(|| sample_velocities(board))
+}
+
+/// A group of particles connected by neighbour pairs.
+#[derive(Debug, Copy, Clone)]
+pub struct Cluster {
+    pub size: usize,
+    /// The sum of the velocities of the particles.
+    pub vel_sum: Vec2,
+    /// The sum of the speeds of the particles.
+    pub speed_sum: f64,
+    /// The sum of the dot products of the particles' velocities with their sampled earlier velocities.
+    pub vel_correlation_sum: f64,
+    /// The sum of the products of the particles' speeds and their sampled earlier speeds.
+    pub speed_product_sum: f64,
+}
+
+impl Cluster {
+    /// How coherently the cluster moves, from 0 (in all directions) to 1 (as one).
+    pub fn coherence(&self) -> f64 {
+        if self.speed_sum > 0.0 { self.vel_sum.mag() / self.speed_sum } else { 0.0 }
+    }
+
+    pub fn mean_speed(&self) -> f64 {
+        self.vel_sum.mag() / self.size as f64
+    }
+
+    /// How well the particles kept their velocities since the sample, from -1 (reversed) to 1 (unchanged).
+    pub fn persistence(&self) -> f64 {
+        if self.speed_product_sum > 0.0 { self.vel_correlation_sum / self.speed_product_sum } else { 0.0 }
+    }
+}
+
+/// The clusters of a board and how often neighbours share a type.
+#[derive(Debug, Clone)]
+pub struct Clustering {
+    /// The clusters with at least the minimum number of particles.
+    pub clusters: Vec<Cluster>,
+    pub neighbour_pairs: usize,
+    pub same_type_pairs: usize,
+    pub type_counts: Vec<usize>,
+}
+
+impl Clustering {
+    /// Particles closer than `cluster_distance` are neighbours and belong to the same cluster. It shouldn't exceed the
+    /// size of a bounding rect. The velocities are compared to `earlier_velocities` for the persistence.
+    pub fn measure(board: &Board, cluster_distance: f64, min_cluster_size: usize, earlier_velocities: &VelocitySample) -> Self {
+        let mut first_idxs = Vec::with_capacity(board.bounding_rects.len());
+        let mut particle_count = 0;
+        for br in board.bounding_rects.iter() {
+            first_idxs.push(particle_count);
+            particle_count += br.len();
+        }
+
+        let mut parents = (0..particle_count).collect::<Vec<_>>();
+        let mut neighbour_pairs = 0;
+        let mut same_type_pairs = 0;
+
+        let mut visit = |i: usize, p: &Particle, j: usize, other_p: &Particle, ox: f64, oy: f64| {
+            let offset = Vec2::new(other_p.pos.x + ox - p.pos.x, other_p.pos.y + oy - p.pos.y);
+            if offset.mag_squared() < cluster_distance * cluster_distance {
+                union(&mut parents, i, j);
+                neighbour_pairs += 1;
+                if p.type_idx == other_p.type_idx {
+                    same_type_pairs += 1;
+                }
+            }
+        };
+
+        for br_y in 0..board.br_count_y {
+            for br_x in 0..board.br_count_x {
+                let this_br_idx = br_y * board.br_count_x + br_x;
+                let this_br = &board.bounding_rects[this_br_idx];
+                let neighbours = board.half_neighbours(br_x, br_y);
+
+                for (pi, p) in this_br.iter().enumerate() {
+                    let i = first_idxs[this_br_idx] + pi;
+
+                    for (other_pi, other_p) in this_br.iter().enumerate().skip(pi + 1) {
+                        visit(i, p, first_idxs[this_br_idx] + other_pi, other_p, 0.0, 0.0);
+                    }
+
+                    for &(br_idx, ox, oy) in neighbours.iter() {
+                        for (other_pi, other_p) in board.bounding_rects[br_idx].iter().enumerate() {
+                            visit(i, p, first_idxs[br_idx] + other_pi, other_p, ox, oy);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut sizes = vec![0; particle_count];
+        let mut vel_sums = vec![vec2::ZERO; particle_count];
+        let mut speed_sums = vec![0.0; particle_count];
+        let mut vel_correlation_sums = vec![0.0; particle_count];
+        let mut speed_product_sums = vec![0.0; particle_count];
+        let mut type_counts = vec![0; board.particle_types.len()];
+        for (i, p) in board.bounding_rects.iter().flatten().enumerate() {
+            let root = find(&mut parents, i);
+            sizes[root] += 1;
+            vel_sums[root] += p.vel;
+            speed_sums[root] += p.vel.mag();
+            if let Some(&earlier_vel) = earlier_velocities.get(&p.id) {
+                vel_correlation_sums[root] += p.vel.dot(earlier_vel);
+                speed_product_sums[root] += p.vel.mag() * earlier_vel.mag();
+            }
+            type_counts[p.type_idx] += 1;
+        }
+
+        let clusters = (0..particle_count)
+            .filter(|&i| sizes[i] >= min_cluster_size.max(1))
+            .map(|i| Cluster {
+                size: sizes[i],
+                vel_sum: vel_sums[i],
+                speed_sum: speed_sums[i],
+                vel_correlation_sum: vel_correlation_sums[i],
+                speed_product_sum: speed_product_sums[i],
+            })
+            .collect();
+
+        Clustering { clusters, neighbour_pairs, same_type_pairs, type_counts }
+    }
+}
+
+impl Scores {
+    /// See `Clustering::measure`.
+    pub fn measure(board: &Board, cluster_distance: f64, min_cluster_size: usize, earlier_velocities: &VelocitySample) -> Self {
+        Self::from_clustering(&Clustering::measure(board, cluster_distance, min_cluster_size, earlier_velocities))
+    }
+
+    pub fn from_clustering(clustering: &Clustering) -> Self {
+        let cluster_count = clustering.clusters.len();
+        let clustered_particle_count = clustering.clusters.iter().map(|c| c.size).sum::<usize>();
+        let coherent_speed = clustering.clusters.iter().map(|c| c.vel_sum.mag()).sum::<f64>();
+        let total_speed = clustering.clusters.iter().map(|c| c.speed_sum).sum::<f64>();
+        let vel_correlation = clustering.clusters.iter().map(|c| c.vel_correlation_sum).sum::<f64>();
+        let speed_product = clustering.clusters.iter().map(|c| c.speed_product_sum).sum::<f64>();
+
+        let mean_cluster_size = if cluster_count > 0 { clustered_particle_count as f64 / cluster_count as f64 } else { 0.0 };
+        let motion_coherence = if total_speed > 0.0 { coherent_speed / total_speed } else { 0.0 };
+        let motion_persistence = if speed_product > 0.0 { vel_correlation / speed_product } else { 0.0 };
+
+        let particle_count = clustering.type_counts.iter().sum::<usize>();
+        let expected_same_type_fraction = clustering.type_counts
+            .iter()
+            .map(|&count| (count as f64 / particle_count.max(1) as f64).powi(2))
+            .sum::<f64>();
+        let type_segregation = if clustering.neighbour_pairs > 0 && expected_same_type_fraction < 1.0 {
+            let same_type_fraction = clustering.same_type_pairs as f64 / clustering.neighbour_pairs as f64;
+            (same_type_fraction - expected_same_type_fraction) / (1.0 - expected_same_type_fraction)
+        } else {
+            0.0
+        };
+
+        Scores { cluster_count, mean_cluster_size, motion_coherence, motion_persistence, type_segregation }
+    }
+
+    /// Motion coherence, weighted up by type segregation and the logarithm of the mean cluster size.
+    pub fn combined(&self) -> f64 {
+        self.motion_coherence * (1.0 + self.type_segregation.max(0.0)) * self.mean_cluster_size.ln_1p()
+    }
+
+    pub fn get(&self, score: Score) -> f64 {
+        match score {
+            Score::ClusterCount => self.cluster_count as f64,
+            Score::MeanClusterSize => self.mean_cluster_size,
+            Score::MotionCoherence => self.motion_coherence,
+            Score::MotionPersistence => self.motion_persistence,
+            Score::TypeSegregation => self.type_segregation,
+            Score::Combined => self.combined(),
+        }
+    }
+}
+
+fn find(parents: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while parents[root] != root {
+        root = parents[root];
+    }
+
+    let mut i = i;
+    while parents[i] != root {
+        let next = parents[i];
+        parents[i] = root;
+        i = next;
+    }
+
+    root
+}
+
+fn union(parents: &mut [usize], i: usize, j: usize) {
+    let root_i = find(parents, i);
+    let root_j = find(parents, j);
+    parents[root_i] = root_j;
+}
@@ -1,14 +1,28 @@
 use std::cmp::Ordering::{Greater, Less};
+use std::collections::{HashMap, HashSet};
+use std::f64::consts::TAU;
 use rand::Rng;
 use rand_xoshiro::rand_core::SeedableRng;
 use rand_xoshiro::Xoshiro256PlusPlus;
-use rand::distributions::Uniform;
+use serde::{Deserialize, Serialize};
+use crate::bonds::{Bond, BondRule};
+use crate::boundary::Boundaries;
+use crate::field::ExternalField;
+use crate::initial_state;
+use crate::integrator::Integrator;
+use crate::obstacles::Obstacles;
 use crate::particle::Particle;
-use crate::particle_type::{ConversionType, ParticleType};
+use crate::reactions::Reaction;
+use crate::particle_type::{ConversionType, Kinetics, ParticleType};
+use crate::rules::Rules;
+use crate::serialization;
+use crate::stats::Counters;
+use crate::thermostat::{standard_normal, Thermostat};
 use crate::vec2;
 use crate::vec2::Vec2;
 
-#[derive(Debug, Clone)]
+/// The complete simulation state. Serializing a board gives a snapshot the simulation can be resumed from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Board {
     pub width: f64,
     pub height: f64,
@@ -20,9 +34,84 @@ pub struct Board {
     pub particle_types: Vec<ParticleType>,
     pub touching_pushing_acc: f64,
     pub resistance: f64,
+    #[serde(default)]
+    pub boundary: Boundaries,
+    /// The number of simulated ticks.
+    #[serde(default)]
+    pub tick: usize,
+    #[serde(default)]
+    pub counters: Counters,
+    #[serde(default)]
+    pub integrator: Integrator,
+    /// The timestep of a tick.
+    #[serde(default = "default_dt")]
+    pub dt: f64,
+    #[serde(default)]
+    pub thermostat: Thermostat,
+    // the random number generators are saved in snapshots so resumed runs stay deterministic
+    /// The noise of the Langevin thermostat.
+    #[serde(default = "default_rng")]
+    pub thermostat_rng: Xoshiro256PlusPlus,
+    /// Decides which touching particles bond.
+    #[serde(default = "default_rng")]
+    pub bond_rng: Xoshiro256PlusPlus,
+    /// Decides the conversions and reactions that aren't certain.
+    #[serde(default = "default_rng")]
+    pub reaction_rng: Xoshiro256PlusPlus,
+    #[serde(default)]
+    pub external_fields: Vec<ExternalField>,
+    #[serde(default)]
+    pub obstacles: Option<Obstacles>,
+    /// The id of the next inserted particle.
+    #[serde(default)]
+    pub next_particle_id: u64,
+    #[serde(default)]
+    pub bond_rules: Vec<BondRule>,
+    #[serde(default)]
+    pub bonds: Vec<Bond>,
+    #[serde(default)]
+    pub reactions: Vec<Reaction>,
+    /// The ids of the particles that touched during the last interaction, collected only for bonds and reactions.
+    #[serde(skip)]
+    contacts: Vec<(u64, u64)>,
+}
+
+/// The cell index and the index in the cell of every particle, by id.
+pub type Locations = HashMap<u64, (usize, usize)>;
+
+fn default_dt() -> f64 {
+    1.0
+}
+
+/// The `stream`-th of the non-overlapping sequences of random numbers generated from the seed.
+fn rng_stream(seed: u64, stream: usize) -> Xoshiro256PlusPlus {
+    let mut rng = Xoshiro256PlusPlus::seed_from_u64(seed);
+    for _ in 0..stream {
+        rng.long_jump();
+    }
+
+    rng
+}
+
+fn default_rng() -> Xoshiro256PlusPlus {
+    Xoshiro256PlusPlus::seed_from_u64(0)
+}
+
+/// Interacts all particles, returning the number of touching pairs and adding their ids to the contacts if given.
+type InteractCells = fn(&mut Board, Option<&mut Vec<(u64, u64)>>) -> usize;
+
+/// Removes the `i`-th particle of the cell, updating the location of the particle that takes its place.
+fn swap_remove_located(br: &mut Vec<Particle>, br_idx: usize, i: usize, locations: Option<&mut Locations>) -> Particle {
+    let p = br.swap_remove(i);
+    if let (Some(locations), Some(moved)) = (locations, br.get(i)) {
+        locations.insert(moved.id, (br_idx, i));
+    }
+
+    p
 }
 
 impl Board {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         particle_count: usize,
         type_count: usize,
@@ -40,83 +129,577 @@ impl Board {
         rule_rng_seed: u64,
         initial_state_rng_seed: u64,
     ) -> Self {
-        let mut rule_rng = Xoshiro256PlusPlus::seed_from_u64(rule_rng_seed);
+        let rules = Rules::generate(
+            type_count,
+            max_field_pulling_acc,
+            max_field_pushing_acc,
+            max_radius,
+            generate_chemistry,
+            color_rng_seed,
+            rule_rng_seed,
+        );
+
+        Self::from_rules(
+            rules,
+            particle_count,
+            width,
+            height,
+            br_count_x,
+            br_count_y,
+            touching_pushing_acc,
+            resistance,
+            initial_state_rng_seed,
+        )
+    }
 
-        let (converts_tos, catalysts_of_type) = if generate_chemistry {
-            new_chemistry(type_count, &mut rule_rng)
-        } else {
-            (vec![], vec![])
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_rules(
+        rules: Rules,
+        particle_count: usize,
+        width: f64,
+        height: f64,
+        br_count_x: usize,
+        br_count_y: usize,
+        touching_pushing_acc: f64,
+        resistance: f64,
+        initial_state_rng_seed: u64,
+    ) -> Self {
+        let particle_types = rules.particle_types;
+        let br_width = width / br_count_x as f64;
+        let br_height = height / br_count_y as f64;
+
+        let initial_br_capacity = (particle_count as f64 / (br_count_x * br_count_y) as f64 * 2.0).round() as usize;
+        let mut bounding_rects = Vec::with_capacity(br_count_x * br_count_y);
+        for _ in 0..(br_count_x * br_count_y) {
+            bounding_rects.push(Vec::with_capacity(initial_br_capacity))
+        }
+
+        let mut board = Board {
+            width,
+            height,
+            bounding_rects,
+            br_count_x,
+            br_count_y,
+            br_width,
+            br_height,
+            particle_types,
+            touching_pushing_acc,
+            resistance,
+            boundary: Boundaries::default(),
+            tick: 0,
+            counters: Counters::default(),
+            integrator: Integrator::default(),
+            dt: default_dt(),
+            thermostat: Thermostat::default(),
+            thermostat_rng: default_rng(),
+            external_fields: Vec::new(),
+            obstacles: None,
+            next_particle_id: 0,
+            bond_rules: rules.bond_rules,
+            bonds: Vec::new(),
+            bond_rng: default_rng(),
+            reaction_rng: default_rng(),
+            reactions: rules.reactions,
+            contacts: Vec::new(),
         };
 
-        let colors = get_colors(type_count, color_rng_seed);
+        let mut initial_state_rng = Xoshiro256PlusPlus::seed_from_u64(initial_state_rng_seed);
 
-        let mut particle_types = Vec::with_capacity(type_count);
-        for i in 0..type_count {
+        board.thermostat_rng = rng_stream(initial_state_rng_seed, 1);
+        board.bond_rng = rng_stream(initial_state_rng_seed, 2);
+        board.reaction_rng = rng_stream(initial_state_rng_seed, 3);
 
-            let ct = if generate_chemistry && converts_tos[i] != i {
-                ConversionType::CONVERTS { converts_to: converts_tos[i], catalysts: catalysts_of_type[i].clone() }
-            } else {
-                ConversionType::INERT
-            };
+        initial_state::place_random(&mut board, particle_count, &mut initial_state_rng);
 
-            let color = colors[i];
+        board
+    }
 
-            let particle_type = ParticleType::new(color, type_count, max_field_pulling_acc, max_field_pushing_acc, max_radius, ct, &mut rule_rng);
+    pub fn load_snapshot(path: &str) -> Result<Self, String> {
+        let mut board: Board = serialization::load(path)?;
 
-            particle_types.push(particle_type)
+        if board.bounding_rects.len() != board.br_count_x * board.br_count_y {
+            return Err(format!("{}: the number of bounding rects doesn't match the grid size.", path));
+        }
+        board.rules().validate().map_err(|e| format!("{}: {}", path, e))?;
+        if board.bounding_rects.iter().flatten().any(|p| p.type_idx >= board.particle_types.len()) {
+            return Err(format!("{}: a particle has an unknown type.", path));
+        }
+        if board.obstacles.as_ref().is_some_and(|o| o.occupied.len() != o.mask_width * o.mask_height || o.occupied.is_empty()) {
+            return Err(format!("{}: the obstacle mask doesn't match its size.", path));
+        }
+        if board.bonds.iter().any(|bond| bond.rule_idx >= board.bond_rules.len()) {
+            return Err(format!("{}: a bond has an unknown rule.", path));
         }
 
-        let br_width = width / br_count_x as f64;
-        let br_height = height / br_count_y as f64;
+        // snapshots from before particles had ids
+        if board.next_particle_id == 0 {
+            for p in board.bounding_rects.iter_mut().flatten() {
+                p.id = board.next_particle_id;
+                board.next_particle_id += 1;
+            }
+        }
 
-        let mut initial_state_rng = Xoshiro256PlusPlus::seed_from_u64(initial_state_rng_seed);
+        Ok(board)
+    }
 
-        let initial_br_capacity = (particle_count as f64 / (br_count_x * br_count_y) as f64 * 2.0).round() as usize;
-        let mut bounding_rects = Vec::with_capacity(br_count_x * br_count_y);
-        for _ in 0..(br_count_x * br_count_y) {
-            bounding_rects.push(Vec::with_capacity(initial_br_capacity))
+    pub fn save_snapshot(&self, path: &str) -> Result<(), String> {
+        serialization::save(self, path)
+    }
+
+    /// Adds a particle at the board position `pos`, wrapped onto the board.
+    pub fn insert_particle(&mut self, type_idx: usize, pos: Vec2, vel: Vec2) {
+        let x = pos.x.rem_euclid(self.width);
+        let y = pos.y.rem_euclid(self.height);
+
+        let br_col = ((x / self.br_width).floor() as usize).min(self.br_count_x - 1);
+        let br_row = ((y / self.br_height).floor() as usize).min(self.br_count_y - 1);
+
+        let p = Particle::new(
+            self.next_particle_id,
+            type_idx,
+            self.particle_types[type_idx].can_convert(),
+            Vec2::new(x % self.br_width, y % self.br_height),
+            vel,
+        );
+
+        self.bounding_rects[br_row * self.br_count_x + br_col].push(p);
+        self.next_particle_id += 1;
+    }
+
+    /// Places the obstacles on the board, removing the particles inside them.
+    pub fn set_obstacles(&mut self, obstacles: Option<Obstacles>) {
+        if let Some(obstacles) = &obstacles {
+            for br_idx in 0..self.bounding_rects.len() {
+                let origin = self.br_origin(br_idx);
+                self.bounding_rects[br_idx].retain(|p| !obstacles.contains(origin + p.pos));
+            }
         }
 
-        for _ in 0..particle_count {
-            let x = initial_state_rng.gen_range(0.0..width);
-            let y = initial_state_rng.gen_range(0.0..height);
-            let t = initial_state_rng.gen_range(0..type_count);
+        self.obstacles = obstacles;
+    }
 
-            let can_convert = match &particle_types[t].conversion_type {
-                ConversionType::INERT => false,
-                ConversionType::CONVERTS { converts_to: _, catalysts: _ } => true,
-            };
+    /// Whether the board position is inside an obstacle.
+    pub fn is_obstructed(&self, pos: Vec2) -> bool {
+        self.obstacles.as_ref().is_some_and(|obstacles| obstacles.contains(pos))
+    }
 
-            let p = Particle::new(
-                t,
-                can_convert,
-                Vec2::new(x % br_width as f64, y % br_height as f64),
-                vec2::ZERO,
-            );
+    /// Removes all particles within `radius` of the board position `pos` and returns how many were removed.
+    pub fn remove_particles_within(&mut self, pos: Vec2, radius: f64) -> usize {
+        let mut removed = 0;
+
+        for br_idx in 0..self.bounding_rects.len() {
+            let origin = self.br_origin(br_idx);
+            let offsets = self.bounding_rects[br_idx]
+                .iter()
+                .map(|p| self.offset_between(pos, origin + p.pos))
+                .collect::<Vec<_>>();
+
+            let mut i = 0;
+            self.bounding_rects[br_idx].retain(|_| {
+                let keep = offsets[i].mag_squared() >= radius * radius;
+                i += 1;
+                keep
+            });
+            removed += offsets.len() - self.bounding_rects[br_idx].len();
+        }
+
+        removed
+    }
+
+    /// Accelerates all particles within `radius` of the board position `pos` towards it (or away from it, if `acc` is
+    /// negative), fading out linearly with distance.
+    pub fn apply_radial_acc(&mut self, pos: Vec2, radius: f64, acc: f64) {
+        for br_idx in 0..self.bounding_rects.len() {
+            let origin = self.br_origin(br_idx);
 
-            let br_col = (x / br_width).floor() as usize;
-            let br_row = (y / br_height).floor() as usize;
+            for i in 0..self.bounding_rects[br_idx].len() {
+                let offset = self.offset_between(origin + self.bounding_rects[br_idx][i].pos, pos);
+                let dist = offset.mag();
 
-            bounding_rects[br_row * br_count_x + br_col].push(p);
+                if dist < radius && dist > 0.0 {
+                    self.bounding_rects[br_idx][i].vel += offset / dist * acc * (1.0 - dist / radius) * self.dt;
+                }
+            }
         }
+    }
 
-        Board { width, height, bounding_rects, br_count_x, br_count_y, br_width, br_height, particle_types, touching_pushing_acc, resistance }
+    /// The board position of the top left corner of a bounding rect.
+    pub fn br_origin(&self, br_idx: usize) -> Vec2 {
+        Vec2::new(
+            (br_idx % self.br_count_x) as f64 * self.br_width,
+            (br_idx / self.br_count_x) as f64 * self.br_height,
+        )
+    }
+
+    /// The shortest offset from one board position to another, taking periodic boundaries into account.
+    pub fn offset_between(&self, from: Vec2, to: Vec2) -> Vec2 {
+        let mut offset = to - from;
+
+        if self.boundary.x.is_periodic() {
+            offset.x -= (offset.x / self.width).round() * self.width;
+        }
+        if self.boundary.y.is_periodic() {
+            offset.y -= (offset.y / self.height).round() * self.height;
+        }
+
+        offset
+    }
+
+    pub fn rules(&self) -> Rules {
+        Rules { particle_types: self.particle_types.clone(), bond_rules: self.bond_rules.clone(), reactions: self.reactions.clone() }
     }
 
     pub fn simulate(&mut self) {
-        for br_y in 0..self.br_count_y {
-            let br_y_plus = (br_y + 1).rem_euclid(self.br_count_y);
+        #[cfg(feature = "parallel")]
+        self.simulate_parallel();
 
-            for br_x in 0..self.br_count_x {
-                let br_x_minus = (br_x as isize - 1).rem_euclid(self.br_count_x as isize) as usize;
-                let br_x_plus = (br_x + 1).rem_euclid(self.br_count_x);
+        #[cfg(not(feature = "parallel"))]
+        self.simulate_serial();
+    }
+
+    /// Simulates one tick on the current thread.
+    pub fn simulate_serial(&mut self) {
+        self.step(Self::interact_cells);
+    }
+
+    /// Simulates one tick, interacting the particles of non-overlapping cells on multiple threads.
+    ///
+    /// The interactions are the same as in `simulate_serial`, but the accelerations are summed up in a different order,
+    /// so the results aren't bit-identical.
+    #[cfg(feature = "parallel")]
+    pub fn simulate_parallel(&mut self) {
+        if self.br_count_x < 3 || self.br_count_y < 2 {
+            self.step(Self::interact_cells);
+        } else {
+            self.step(Self::interact_cells_parallel);
+        }
+    }
+
+    /// Advances the board by one timestep, using `interact_cells` to calculate the accelerations.
+    fn step(&mut self, interact_cells: InteractCells) {
+        let dt = self.dt;
+        let resistance = self.resistance;
+
+        // bonds and reactions look particles up by id, so they are located once and followed between cells
+        let mut locations = (!self.bonds.is_empty() || !self.bond_rules.is_empty() || !self.reactions.is_empty())
+            .then(|| self.particle_locations());
+
+        match self.integrator {
+            Integrator::Euler => {
+                self.calculate_accelerations(interact_cells, locations.as_ref());
+                self.move_particles();
+                self.update_particles(|p| p.vel += (p.acc + drag(p.vel, resistance)) * dt);
+                self.migrate_particles(locations.as_mut());
+            },
+            Integrator::SemiImplicitEuler => {
+                self.calculate_accelerations(interact_cells, locations.as_ref());
+                self.update_particles(|p| {
+                    p.vel += p.acc * dt;
+                    p.vel += drag(p.vel, resistance) * dt;
+                });
+                self.move_particles();
+                self.migrate_particles(locations.as_mut());
+            },
+            Integrator::VelocityVerlet => {
+                self.update_particles(|p| p.vel += p.acc * (0.5 * dt));
+                self.move_particles();
+                self.migrate_particles(locations.as_mut());
+                self.calculate_accelerations(interact_cells, locations.as_ref());
+                self.update_particles(|p| {
+                    p.vel += p.acc * (0.5 * dt);
+                    p.vel += drag(p.vel, resistance) * dt;
+                });
+            },
+        }
+
+        self.apply_thermostat();
+        if let Some(locations) = &locations {
+            self.update_bonds(locations);
+        }
+        self.convert_particles();
+        if let Some(locations) = &locations {
+            self.react_particles(locations);
+        }
+        self.tick += 1;
+    }
+
+    /// Moves particles by their velocity. A particle that would enter an obstacle stays in place and bounces off it,
+    /// reversing its velocity along the blocked axes.
+    fn move_particles(&mut self) {
+        let dt = self.dt;
+
+        if self.obstacles.is_none() {
+            self.update_particles(|p| p.pos += p.vel * dt);
+            return;
+        }
+
+        let origins = (0..self.bounding_rects.len()).map(|br_idx| self.br_origin(br_idx)).collect::<Vec<_>>();
+        let obstacles = self.obstacles.as_ref().unwrap();
+
+        for (br, origin) in self.bounding_rects.iter_mut().zip(origins) {
+            for p in br.iter_mut() {
+                let old_pos = origin + p.pos;
+                let new_pos = old_pos + p.vel * dt;
+
+                // particles spawned inside an obstacle move freely until they are out
+                if !obstacles.contains(new_pos) || obstacles.contains(old_pos) {
+                    p.pos += p.vel * dt;
+                    continue;
+                }
+
+                let blocked_x = obstacles.contains(Vec2::new(new_pos.x, old_pos.y));
+                let blocked_y = obstacles.contains(Vec2::new(old_pos.x, new_pos.y));
+                if blocked_x || !blocked_y {
+                    p.vel.x = -p.vel.x;
+                }
+                if blocked_y || !blocked_x {
+                    p.vel.y = -p.vel.y;
+                }
+            }
+        }
+    }
+
+    /// The cell index and the index in the cell of every particle, by id. Locations change whenever particles move
+    /// between cells or are removed.
+    pub fn particle_locations(&self) -> Locations {
+        self.bounding_rects
+            .iter()
+            .enumerate()
+            .flat_map(|(br_idx, br)| br.iter().enumerate().map(move |(i, p)| (p.id, (br_idx, i))))
+            .collect()
+    }
+
+    /// The cell index and the index in the cell of the particle with the id. This searches every cell, so use
+    /// `particle_locations` to look up many particles at once.
+    pub fn locate(&self, id: u64) -> Option<(usize, usize)> {
+        self.bounding_rects.iter().enumerate().find_map(|(br_idx, br)| {
+            br.iter().position(|p| p.id == id).map(|i| (br_idx, i))
+        })
+    }
+
+    /// The particle with the id and its board position.
+    pub fn find_particle(&self, id: u64) -> Option<(&Particle, Vec2)> {
+        let (br_idx, i) = self.locate(id)?;
+        let p = &self.bounding_rects[br_idx][i];
+
+        Some((p, self.br_origin(br_idx) + p.pos))
+    }
+
+    /// The board position of the first particle of a bond and the offset to the second one, or `None` if one of them
+    /// is gone.
+    fn bond_span(&self, locations: &Locations, bond: &Bond) -> Option<(Vec2, Vec2)> {
+        let &(br_a, i_a) = locations.get(&bond.a)?;
+        let &(br_b, i_b) = locations.get(&bond.b)?;
 
-                let br_idxs_and_p_offsets = vec![
-                    (br_y * self.br_count_x + br_x_plus, self.br_width, 0.0),
-                    (br_y_plus * self.br_count_x + br_x_minus, -self.br_width, self.br_height),
-                    (br_y_plus * self.br_count_x + br_x, 0.0, self.br_height),
-                    (br_y_plus * self.br_count_x + br_x_plus, self.br_width, self.br_height),
-                ];
+        let pos_a = self.br_origin(br_a) + self.bounding_rects[br_a][i_a].pos;
+        let pos_b = self.br_origin(br_b) + self.bounding_rects[br_b][i_b].pos;
+
+        Some((pos_a, self.offset_between(pos_a, pos_b)))
+    }
+
+    /// The start and end board positions of each bond. Bonds across periodic edges end outside the board.
+    pub fn bond_segments(&self) -> Vec<(Vec2, Vec2)> {
+        let locations = self.particle_locations();
+
+        self.bonds
+            .iter()
+            .filter_map(|bond| self.bond_span(&locations, bond))
+            .map(|(pos, offset)| (pos, pos + offset))
+            .collect()
+    }
+
+    fn apply_bond_forces(&mut self, locations: &Locations) {
+        for bond_idx in 0..self.bonds.len() {
+            let bond = self.bonds[bond_idx];
+            let Some((_, offset)) = self.bond_span(locations, &bond) else {
+                continue;
+            };
+            let dist = offset.mag();
+            if dist == 0.0 {
+                continue;
+            }
+
+            let (br_a, i_a) = locations[&bond.a];
+            let (br_b, i_b) = locations[&bond.b];
+            let rule = &self.bond_rules[bond.rule_idx];
+            let dir = offset / dist;
+            let approach_speed = (self.bounding_rects[br_b][i_b].vel - self.bounding_rects[br_a][i_a].vel).dot(dir);
+            let force = dir * (rule.stiffness * (dist - rule.rest_length) + rule.damping * approach_speed);
+
+            let mass_a = self.particle_types[self.bounding_rects[br_a][i_a].type_idx].mass;
+            let mass_b = self.particle_types[self.bounding_rects[br_b][i_b].type_idx].mass;
+            self.bounding_rects[br_a][i_a].acc += force / mass_a;
+            self.bounding_rects[br_b][i_b].acc -= force / mass_b;
+        }
+    }
+
+    /// Breaks overstretched bonds and those of removed particles, then bonds touching particles by the bond rules.
+    fn update_bonds(&mut self, locations: &Locations) {
+        if self.bond_rules.is_empty() && self.bonds.is_empty() {
+            return;
+        }
+
+        let keep = self.bonds
+            .iter()
+            .map(|bond| {
+                let max_length = self.bond_rules[bond.rule_idx].rest_length + self.bond_rules[bond.rule_idx].max_stretch;
+                self.bond_span(locations, bond).is_some_and(|(_, offset)| offset.mag() <= max_length)
+            })
+            .collect::<Vec<_>>();
+        let mut keep = keep.into_iter();
+        self.bonds.retain(|_| keep.next().unwrap());
+
+        let mut bonded = self.bonds.iter().map(|bond| (bond.a.min(bond.b), bond.a.max(bond.b))).collect::<HashSet<_>>();
+
+        let candidates = self.touching_pairs(locations)
+            .filter_map(|(a, b)| {
+                let rule_idx = self.bond_rules.iter().position(|rule| rule.applies_to(a.type_idx, b.type_idx))?;
+                Some((a.id, b.id, rule_idx))
+            })
+            .collect::<Vec<_>>();
+
+        for (a, b, rule_idx) in candidates {
+            if bonded.contains(&(a.min(b), a.max(b))) {
+                continue;
+            }
+
+            if self.bond_rng.gen::<f64>() < self.bond_rules[rule_idx].probability {
+                self.bonds.push(Bond { a, b, rule_idx });
+                bonded.insert((a.min(b), a.max(b)));
+            }
+        }
+    }
+
+    /// The particles that touched during the last interaction and are still on the board.
+    fn touching_pairs<'a>(&'a self, locations: &'a Locations) -> impl Iterator<Item = (&'a Particle, &'a Particle)> {
+        self.contacts.iter().filter_map(|(a, b)| {
+            let &(br_a, i_a) = locations.get(a)?;
+            let &(br_b, i_b) = locations.get(b)?;
+
+            Some((&self.bounding_rects[br_a][i_a], &self.bounding_rects[br_b][i_b]))
+        })
+    }
+
+    fn apply_thermostat(&mut self) {
+        let dt = self.dt;
+
+        match self.thermostat {
+            Thermostat::None => {},
+            Thermostat::Langevin { temperature, friction } => {
+                // the exact solution of the Ornstein-Uhlenbeck process over the timestep, stable for any dt
+                let damping = (-friction * dt).exp();
+                let noise_variance = (1.0 - damping * damping) * temperature;
+
+                let particle_types = &self.particle_types;
+                let rng = &mut self.thermostat_rng;
+                for p in self.bounding_rects.iter_mut().flatten() {
+                    let noise = (noise_variance / particle_types[p.type_idx].mass).sqrt();
+                    p.vel.x = p.vel.x * damping + noise * standard_normal(rng);
+                    p.vel.y = p.vel.y * damping + noise * standard_normal(rng);
+                }
+            },
+            Thermostat::Berendsen { temperature, time_constant } => {
+                let current = self.kinetic_temperature();
+                if current > 0.0 {
+                    let scale = (1.0 + dt / time_constant * (temperature / current - 1.0)).max(0.0).sqrt();
+                    self.update_particles(|p| p.vel *= scale);
+                }
+            },
+        }
+    }
+
+    /// The mean kinetic energy per particle, which is the temperature of two degrees of freedom with a Boltzmann
+    /// constant of 1.
+    pub fn kinetic_temperature(&self) -> f64 {
+        let mut energy = 0.0;
+        let mut count = 0;
+        for p in self.bounding_rects.iter().flatten() {
+            energy += 0.5 * self.particle_types[p.type_idx].mass * p.vel.mag_squared();
+            count += 1;
+        }
+
+        if count > 0 { energy / count as f64 } else { 0.0 }
+    }
+
+    /// `locations` are given when bonds or reactions need the touching particles.
+    fn calculate_accelerations(&mut self, interact_cells: InteractCells, locations: Option<&Locations>) {
+        self.update_particles(|p| p.acc = vec2::ZERO);
+
+        let mut contacts = std::mem::take(&mut self.contacts);
+        contacts.clear();
+        self.counters.touching_pairs = interact_cells(self, locations.is_some().then_some(&mut contacts));
+        self.contacts = contacts;
+
+        self.apply_external_fields();
+        if let Some(locations) = locations.filter(|_| !self.bonds.is_empty()) {
+            self.apply_bond_forces(locations);
+        }
+    }
+
+    fn apply_external_fields(&mut self) {
+        if self.external_fields.is_empty() {
+            return;
+        }
+
+        for br_idx in 0..self.bounding_rects.len() {
+            let origin = self.br_origin(br_idx);
+
+            for i in 0..self.bounding_rects[br_idx].len() {
+                let p = &self.bounding_rects[br_idx][i];
+                let acc = self.external_fields
+                    .iter()
+                    .filter(|field| field.applies_to(p.type_idx))
+                    .fold(vec2::ZERO, |acc, field| acc + field.acc(self, origin + p.pos, p.vel));
+
+                self.bounding_rects[br_idx][i].acc += acc;
+            }
+        }
+    }
+
+    fn update_particles(&mut self, f: impl Fn(&mut Particle)) {
+        self.bounding_rects.iter_mut().flatten().for_each(f);
+    }
+
+    /// The indices and particle position offsets of the cells a cell interacts with, other than itself, sorted by
+    /// index. Each pair of neighbouring cells is only visited once, from the one above or to the left. Cells only
+    /// interact across the edges of the board along periodic axes.
+    pub(crate) fn half_neighbours(&self, br_x: usize, br_y: usize) -> Vec<(usize, f64, f64)> {
+        let wraps_x = self.boundary.x.is_periodic();
+        let wraps_y = self.boundary.y.is_periodic();
+
+        let br_y_plus = (br_y + 1).rem_euclid(self.br_count_y);
+        let br_x_minus = (br_x as isize - 1).rem_euclid(self.br_count_x as isize) as usize;
+        let br_x_plus = (br_x + 1).rem_euclid(self.br_count_x);
+
+        let has_x_minus = wraps_x || br_x > 0;
+        let has_x_plus = wraps_x || br_x + 1 < self.br_count_x;
+        let has_y_plus = wraps_y || br_y + 1 < self.br_count_y;
+
+        let mut br_idxs_and_p_offsets = Vec::with_capacity(4);
+        if has_x_plus {
+            br_idxs_and_p_offsets.push((br_y * self.br_count_x + br_x_plus, self.br_width, 0.0));
+        }
+        if has_y_plus {
+            if has_x_minus {
+                br_idxs_and_p_offsets.push((br_y_plus * self.br_count_x + br_x_minus, -self.br_width, self.br_height));
+            }
+            br_idxs_and_p_offsets.push((br_y_plus * self.br_count_x + br_x, 0.0, self.br_height));
+            if has_x_plus {
+                br_idxs_and_p_offsets.push((br_y_plus * self.br_count_x + br_x_plus, self.br_width, self.br_height));
+            }
+        }
+        br_idxs_and_p_offsets.sort_by_key(|(idx, _, _)| *idx);
+
+        br_idxs_and_p_offsets
+    }
+
+    /// Returns the number of touching pairs, adding their ids to `contacts` if given.
+    fn interact_cells(&mut self, mut contacts: Option<&mut Vec<(u64, u64)>>) -> usize {
+        let mut touching_pairs = 0;
+
+        for br_y in 0..self.br_count_y {
+            for br_x in 0..self.br_count_x {
+                let br_idxs_and_p_offsets = self.half_neighbours(br_x, br_y);
 
                 let this_br_idx = br_y * self.br_count_x + br_x;
 
@@ -131,93 +714,283 @@ impl Board {
                     }
                 }
 
-                br_idxs_and_p_offsets_before_this.sort_by(|(idx1, _, _), (idx2, _, _)| idx1.cmp(idx2));
-                br_idxs_and_p_offsets_after_this.sort_by(|(idx1, _, _), (idx2, _, _)| idx1.cmp(idx2));
-
                 let (brs_before, brs_temp) = self.bounding_rects.split_at_mut(this_br_idx);
                 let (brs_temp, brs_after) = brs_temp.split_at_mut(1);
                 let this_br = &mut brs_temp[0];
 
-                let mut brs_and_p_offsets_before_this = vec![];
+                let mut brs_and_p_offsets = vec![];
                 let mut brs_before_iter = brs_before.iter_mut();
                 let mut last_idx = 0;
                 for (i, (idx, ox, oy)) in br_idxs_and_p_offsets_before_this.iter().enumerate() {
                     let temp = last_idx;
                     last_idx = *idx;
                     let nth = *idx - temp - if i == 0 { 0 } else { 1 };
-                    brs_and_p_offsets_before_this.push((brs_before_iter.nth(nth).unwrap().as_mut_slice(), *ox, *oy));
+                    brs_and_p_offsets.push((brs_before_iter.nth(nth).unwrap().as_mut_slice(), *ox, *oy));
                 }
 
-                let mut brs_and_p_offsets_after_this = vec![];
                 let mut brs_after_iter = brs_after.iter_mut();
                 let mut last_idx = this_br_idx + 1;
                 for (i, (idx, ox, oy)) in br_idxs_and_p_offsets_after_this.iter().enumerate() {
                     let temp = last_idx;
                     last_idx = *idx;
                     let nth = *idx - temp - if i == 0 { 0 } else { 1 };
-                    brs_and_p_offsets_after_this.push((brs_after_iter.nth(nth).unwrap().as_mut_slice(), *ox, *oy));
+                    brs_and_p_offsets.push((brs_after_iter.nth(nth).unwrap().as_mut_slice(), *ox, *oy));
                 }
 
-                for p_idx in 0..this_br.len() {
-                    let mut this_br_iter = this_br.iter_mut().skip(p_idx);
-                    let p = this_br_iter.next().unwrap();
+                touching_pairs += Self::interact_cell(this_br, &mut brs_and_p_offsets, &self.particle_types, self.touching_pushing_acc, contacts.as_deref_mut());
+            }
+        }
 
-                    for (ps, ox, oy) in brs_and_p_offsets_before_this.iter_mut().chain(brs_and_p_offsets_after_this.iter_mut()) {
-                        for other_p in ps.iter_mut() {
-                            Self::interact(p, other_p, *ox, *oy, &self.particle_types, self.touching_pushing_acc);
-                        }
+        touching_pairs
+    }
+
+    /// A cell writes to itself and to its half-neighbours, which span 3 columns and 2 rows. Cells are split into
+    /// groups whose columns are at least 3 apart or whose rows are at least 2 apart, so the cells of one group never
+    /// write to the same cell and can be processed in parallel. The groups themselves are processed one by one.
+    #[cfg(feature = "parallel")]
+    fn interact_cells_parallel(&mut self, mut contacts: Option<&mut Vec<(u64, u64)>>) -> usize {
+        use rayon::prelude::*;
+
+        let mut touching_pairs = 0;
+
+        let col_groups = stripe_groups(self.br_count_x, 3);
+        let row_groups = stripe_groups(self.br_count_y, 2);
+        let col_group_count = col_groups.iter().max().unwrap() + 1;
+        let row_group_count = row_groups.iter().max().unwrap() + 1;
+
+        for row_group in 0..row_group_count {
+            for col_group in 0..col_group_count {
+                let mut tasks = Vec::new();
+                for br_y in (0..self.br_count_y).filter(|&br_y| row_groups[br_y] == row_group) {
+                    for br_x in (0..self.br_count_x).filter(|&br_x| col_groups[br_x] == col_group) {
+                        let this_br_idx = br_y * self.br_count_x + br_x;
+                        let this_br = std::mem::take(&mut self.bounding_rects[this_br_idx]);
+
+                        let neighbours = self.half_neighbours(br_x, br_y)
+                            .into_iter()
+                            .map(|(idx, ox, oy)| (idx, std::mem::take(&mut self.bounding_rects[idx]), ox, oy))
+                            .collect::<Vec<_>>();
+
+                        tasks.push((this_br_idx, this_br, neighbours));
+                    }
+                }
+
+                let particle_types = &self.particle_types;
+                let touching_pushing_acc = self.touching_pushing_acc;
+                let record_contacts = contacts.is_some();
+                let results = tasks.par_iter_mut().map(|(_, this_br, neighbours)| {
+                    let mut brs_and_p_offsets = neighbours
+                        .iter_mut()
+                        .map(|(_, br, ox, oy)| (br.as_mut_slice(), *ox, *oy))
+                        .collect::<Vec<_>>();
+
+                    let mut task_contacts = Vec::new();
+                    let task_touching_pairs = Self::interact_cell(
+                        this_br,
+                        &mut brs_and_p_offsets,
+                        particle_types,
+                        touching_pushing_acc,
+                        record_contacts.then_some(&mut task_contacts),
+                    );
+
+                    (task_touching_pairs, task_contacts)
+                }).collect::<Vec<_>>();
+
+                for (task_touching_pairs, task_contacts) in results {
+                    touching_pairs += task_touching_pairs;
+                    if let Some(contacts) = contacts.as_deref_mut() {
+                        contacts.extend(task_contacts);
                     }
+                }
 
-                    for other_p in this_br_iter {
-                        Self::interact(p, other_p, 0.0, 0.0, &self.particle_types, self.touching_pushing_acc);
+                for (this_br_idx, this_br, neighbours) in tasks {
+                    self.bounding_rects[this_br_idx] = this_br;
+                    for (idx, br, _, _) in neighbours {
+                        self.bounding_rects[idx] = br;
                     }
                 }
             }
         }
 
-        self.bounding_rects.iter_mut().for_each(|br| {
-            for p in br.iter_mut() {
-                let vel_mag = p.vel.x.hypot(p.vel.y);
-                p.vel += Vec2::new(p.vel.x * vel_mag * -self.resistance, p.vel.y * vel_mag * -self.resistance);
-
-                p.pos = p.pos + p.vel;
-
-                if !p.can_convert {
-                    let ct = &self.particle_types[p.type_idx].conversion_type;
-                    match *ct {
-                        ConversionType::CONVERTS { converts_to, catalysts: _ } => {
-                            p.type_idx = converts_to;
-                            p.can_convert = true;
-                        },
-                        ConversionType::INERT => {},
+        touching_pairs
+    }
+
+    /// Interacts the particles of a cell with each other and with the particles of its half-neighbours. Returns the
+    /// number of touching pairs, adding their ids to `contacts` if given.
+    fn interact_cell(
+        this_br: &mut [Particle],
+        brs_and_p_offsets: &mut [(&mut [Particle], f64, f64)],
+        particle_types: &[ParticleType],
+        pushing_acc: f64,
+        mut contacts: Option<&mut Vec<(u64, u64)>>,
+    ) -> usize {
+        let mut touching_pairs = 0;
+        let mut count = |p: &Particle, other_p: &Particle, touching: bool| {
+            if touching {
+                touching_pairs += 1;
+                if let Some(contacts) = contacts.as_deref_mut() {
+                    contacts.push((p.id, other_p.id));
+                }
+            }
+        };
+
+        for p_idx in 0..this_br.len() {
+            let mut this_br_iter = this_br.iter_mut().skip(p_idx);
+            let p = this_br_iter.next().unwrap();
+
+            for (ps, ox, oy) in brs_and_p_offsets.iter_mut() {
+                for other_p in ps.iter_mut() {
+                    let touching = Self::interact(p, other_p, *ox, *oy, particle_types, pushing_acc);
+                    count(p, other_p, touching);
+                }
+            }
+
+            for other_p in this_br_iter {
+                let touching = Self::interact(p, other_p, 0.0, 0.0, particle_types, pushing_acc);
+                count(p, other_p, touching);
+            }
+        }
+
+        touching_pairs
+    }
+
+    /// Converts the particles marked by their contacts with catalysts, and those whose chance of not converting didn't
+    /// come up.
+    fn convert_particles(&mut self) {
+        for p in self.bounding_rects.iter_mut().flatten() {
+            let ConversionType::CONVERTS { converts_to, catalysts: _ } = self.particle_types[p.type_idx].conversion_type else {
+                continue;
+            };
+            let energy_release = self.particle_types[p.type_idx].kinetics.energy_release;
+
+            if p.can_convert && p.no_reaction_chance < 1.0 && self.reaction_rng.gen::<f64>() >= p.no_reaction_chance {
+                p.can_convert = false;
+            }
+            p.no_reaction_chance = 1.0;
+
+            if !p.can_convert {
+                p.type_idx = converts_to;
+                p.can_convert = true;
+                self.counters.conversions += 1;
+
+                if energy_release != 0.0 {
+                    release_energy(p, self.particle_types[converts_to].mass, energy_release, &mut self.reaction_rng);
+                }
+            }
+        }
+    }
+
+    /// Lets touching pairs react by the first matching reaction. Each particle reacts at most once per tick.
+    fn react_particles(&mut self, locations: &Locations) {
+        if self.reactions.is_empty() {
+            return;
+        }
+
+        // (first reactant id, second reactant id, reaction index), with the reactants in the order of the reaction
+        let candidates = self.touching_pairs(locations)
+            .filter_map(|(a, b)| {
+                self.reactions.iter().enumerate().find_map(|(reaction_idx, reaction)| {
+                    let in_order = reaction.matches(a.type_idx, b.type_idx)?;
+                    let impact_energy = {
+                        let (mass_a, mass_b) = (self.particle_types[a.type_idx].mass, self.particle_types[b.type_idx].mass);
+                        0.5 * mass_a * mass_b / (mass_a + mass_b) * (b.vel - a.vel).mag_squared()
                     };
+                    (impact_energy >= reaction.activation_energy)
+                        .then_some(if in_order { (a.id, b.id, reaction_idx) } else { (b.id, a.id, reaction_idx) })
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut reacted = HashSet::new();
+        let mut removed = HashSet::new();
+        let mut spawned = Vec::new();
+
+        for (a, b, reaction_idx) in candidates {
+            if reacted.contains(&a) || reacted.contains(&b) {
+                continue;
+            }
+
+            let rate = self.reactions[reaction_idx].rate;
+            if rate < 1.0 && self.reaction_rng.gen::<f64>() >= rate {
+                continue;
+            }
+            reacted.insert(a);
+            reacted.insert(b);
+            self.counters.reactions += 1;
+
+            let (br_a, i_a) = locations[&a];
+            let (br_b, i_b) = locations[&b];
+            let pos_a = self.br_origin(br_a) + self.bounding_rects[br_a][i_a].pos;
+            let pos_b = self.br_origin(br_b) + self.bounding_rects[br_b][i_b].pos;
+            let midpoint = pos_a + self.offset_between(pos_a, pos_b) / 2.0;
+            let (vel_a, vel_b) = (self.bounding_rects[br_a][i_a].vel, self.bounding_rects[br_b][i_b].vel);
+
+            let products = self.reactions[reaction_idx].products.clone();
+            for (k, (id, br_idx, i)) in [(a, br_a, i_a), (b, br_b, i_b)].into_iter().enumerate() {
+                match products.get(k) {
+                    Some(&type_idx) => {
+                        let p = &mut self.bounding_rects[br_idx][i];
+                        p.type_idx = type_idx;
+                        p.can_convert = self.particle_types[type_idx].can_convert();
+                        p.no_reaction_chance = 1.0;
+                    },
+                    None => {
+                        removed.insert(id);
+                    },
                 }
             }
-        });
+            spawned.extend(products.iter().skip(2).map(|&type_idx| (type_idx, midpoint, (vel_a + vel_b) / 2.0)));
+        }
 
+        if !removed.is_empty() {
+            self.bounding_rects.iter_mut().for_each(|br| br.retain(|p| !removed.contains(&p.id)));
+        }
+        for (type_idx, pos, vel) in spawned {
+            self.insert_particle(type_idx, pos, vel);
+        }
+    }
+
+    /// Moves the particles that left their bounding rects to the right ones and applies the boundaries, keeping
+    /// `locations` up to date if given.
+    fn migrate_particles(&mut self, mut locations: Option<&mut Locations>) {
         for by in 0..self.br_count_y {
             for bx in 0..self.br_count_x {
                 let this_br_idx = by * self.br_count_x + bx;
 
                 let mut pi = 0;
                 while pi < self.bounding_rects[this_br_idx].len() {
-                    let mut p = &mut self.bounding_rects[this_br_idx][pi];
+                    let p = &mut self.bounding_rects[this_br_idx][pi];
+
+                    let mut keep = true;
+                    if (bx == 0 && p.pos.x < 0.0) || (bx + 1 == self.br_count_x && p.pos.x >= self.br_width) {
+                        keep &= self.boundary.x.apply(&mut p.pos.x, &mut p.vel.x, self.br_width);
+                    }
+                    if (by == 0 && p.pos.y < 0.0) || (by + 1 == self.br_count_y && p.pos.y >= self.br_height) {
+                        keep &= self.boundary.y.apply(&mut p.pos.y, &mut p.vel.y, self.br_height);
+                    }
+                    if !keep {
+                        let p = swap_remove_located(&mut self.bounding_rects[this_br_idx], this_br_idx, pi, locations.as_deref_mut());
+                        if let Some(locations) = locations.as_deref_mut() {
+                            locations.remove(&p.id);
+                        }
+                        continue;
+                    }
 
                     let br_ox = if p.pos.x < 0.0 {
-                        p.pos.x = p.pos.x + self.br_width;
+                        p.pos.x += self.br_width;
                         -1
                     } else if p.pos.x >= self.br_width {
-                        p.pos.x = p.pos.x - self.br_width;
+                        p.pos.x -= self.br_width;
                         1
                     } else {
                         0
                     };
 
                     let br_oy = if p.pos.y < 0.0 {
-                        p.pos.y = p.pos.y + self.br_height;
+                        p.pos.y += self.br_height;
                         -1
                     } else if p.pos.y >= self.br_height {
-                        p.pos.y = p.pos.y - self.br_height;
+                        p.pos.y -= self.br_height;
                         1
                     } else {
                         0
@@ -231,22 +1004,36 @@ impl Board {
                     let new_bx = (bx as isize + br_ox).rem_euclid(self.br_count_x as isize) as usize;
                     let new_by = (by as isize + br_oy).rem_euclid(self.br_count_y as isize) as usize;
 
-                    let p = self.bounding_rects[this_br_idx].swap_remove(pi);
-                    self.bounding_rects[new_by * self.br_count_x + new_bx].push(p);
+                    let new_br_idx = new_by * self.br_count_x + new_bx;
+                    let p = swap_remove_located(&mut self.bounding_rects[this_br_idx], this_br_idx, pi, locations.as_deref_mut());
+                    if let Some(locations) = locations.as_deref_mut() {
+                        locations.insert(p.id, (new_br_idx, self.bounding_rects[new_br_idx].len()));
+                    }
+                    self.bounding_rects[new_br_idx].push(p);
                 }
             }
         }
     }
 
-    fn interact(p1: &mut Particle, p2: &mut Particle, ox: f64, oy: f64, particle_types: &Vec<ParticleType>, pushing_acc: f64) {
+    /// Returns whether the particles are touching.
+    fn interact(p1: &mut Particle, p2: &mut Particle, ox: f64, oy: f64, particle_types: &[ParticleType], pushing_acc: f64) -> bool {
         let pox = p2.pos.x + ox - p1.pos.x;
         let poy = p2.pos.y + oy - p1.pos.y;
         let dist = (pox).hypot(poy);
+        // particles at the same spot (e.g. stacked in a corner by a clamping boundary) have no direction between
+        // them, so they are pushed apart along x instead of dividing by zero
+        let (dir_x, dir_y) = if dist > 0.0 { (pox / dist, poy / dist) } else { (1.0, 0.0) };
+        let type1 = &particle_types[p1.type_idx];
+        let type2 = &particle_types[p2.type_idx];
+        let contact_dist = type1.radius + type2.radius;
+        let touching = dist < contact_dist;
 
 
-        if dist < 1.0 {
+        if touching {
             // chemistry
 
+            let impact_energy = 0.5 * type1.mass * type2.mass / (type1.mass + type2.mass) * (p2.vel - p1.vel).mag_squared();
+
             // p1
             if p1.can_convert {
                 let ct = &particle_types[p1.type_idx].conversion_type;
@@ -254,7 +1041,7 @@ impl Board {
                     ConversionType::INERT => {},
                     ConversionType::CONVERTS { converts_to: _, catalysts } => {
                         if catalysts[p2.type_idx] {
-                            p1.can_convert = false;
+                            touch_catalyst(p1, &type1.kinetics, impact_energy);
                         }
                     }
                 }
@@ -267,7 +1054,7 @@ impl Board {
                     ConversionType::INERT => {},
                     ConversionType::CONVERTS { converts_to: _, catalysts } => {
                         if catalysts[p1.type_idx] {
-                            p2.can_convert = false;
+                            touch_catalyst(p2, &type2.kinetics, impact_energy);
                         }
                     }
                 }
@@ -275,10 +1062,10 @@ impl Board {
 
 
             // acceleration due to touching particles pushing each other
-            let acc_coef = 1.0 - dist;
-            let this_acc = Vec2::new(pox / dist * acc_coef * -pushing_acc, poy / dist * acc_coef * -pushing_acc);
-            p1.vel += this_acc;
-            p2.vel -= this_acc;
+            let acc_coef = contact_dist - dist;
+            let this_acc = Vec2::new(dir_x * acc_coef * -pushing_acc, dir_y * acc_coef * -pushing_acc);
+            p1.acc += this_acc / type1.mass;
+            p2.acc -= this_acc / type2.mass;
         }
 
 
@@ -303,7 +1090,7 @@ impl Board {
 
             let acc = acc_left * (1.0 - inc) + acc_right * inc;
 
-            p1.vel += Vec2::new(pox / dist * acc, poy / dist * acc);
+            p1.acc += Vec2::new(dir_x * acc, dir_y * acc) / type1.mass;
         }
 
         // p2
@@ -325,145 +1112,52 @@ impl Board {
 
             let acc = acc_left * (1.0 - inc) + acc_right * inc;
 
-            p2.vel += Vec2::new(-pox / dist * acc, -poy / dist * acc);
+            p2.acc += Vec2::new(-dir_x * acc, -dir_y * acc) / type2.mass;
         }
+
+        touching
     }
 }
 
-fn new_chemistry(type_count: usize, rule_rng: &mut impl Rng) -> (Vec<usize>, Vec<Vec<bool>>) {
-    let mut converts_tos = vec![None; type_count];
-    {
-        let mut i = 0;
-        let mut i2 = 0;
-        let mut self_available = true;
-        let mut blank_types_left = type_count;
-        while i < type_count {
-            let mut until_target = rule_rng.gen_range(if self_available {
-                0..blank_types_left
-            } else {
-                0..(blank_types_left - 1)
-            });
-
-            //println!("{}", until_target);
-
-            if until_target == 0 {
-                converts_tos[i2] = Some(i);
-                //println!("v[{}] = {}", i2, i);
-                while converts_tos.get(i).is_some() && converts_tos[i].is_some() {
-                    i += 1;
-                }
-                i2 = i;
-                self_available = true;
-                blank_types_left -= 1;
-                continue;
-            }
-
-            until_target -= 1;
-
-            let mut target_idx = i + 1;
-            loop {
-                if converts_tos[target_idx].is_none() && target_idx != i2 {
-                    if until_target == 0 {
-                        break;
-                    } else {
-                        until_target -= 1;
-                        target_idx += 1;
-                    }
-                } else {
-                    target_idx += 1;
-                }
-            }
-
-            converts_tos[i2] = Some(target_idx);
-            //println!("v[{}] = {}", i2, target_idx);
-            i2 = target_idx;
+/// Marks the particle for conversion if the contact with a catalyst certainly converts it, and otherwise lowers the
+/// chance of it not converting.
+fn touch_catalyst(p: &mut Particle, kinetics: &Kinetics, impact_energy: f64) {
+    let chance = kinetics.contact_chance(impact_energy);
 
-            blank_types_left -= 1;
-        }
+    if chance >= 1.0 {
+        p.can_convert = false;
+    } else {
+        p.no_reaction_chance *= 1.0 - chance;
     }
-    let converts_tos = converts_tos.iter().map(|v| v.unwrap()).collect::<Vec<_>>();
-
-    let mut catalysts_of_type = Vec::with_capacity(type_count);
-    for j in 0..type_count {
-        if converts_tos[j] == j {
-            catalysts_of_type.push(Vec::new());
-            continue;
-        }
-
-        let mut catalysts = vec![false; type_count];
-
-        let mut has_catalyst = false;
-        while !has_catalyst {
-            for i in 0..type_count {
-                let val = rule_rng.gen::<bool>();
-                if val {
-                    has_catalyst = true;
-                }
-                catalysts[i] = val;
-            }
-        }
+}
 
-        catalysts_of_type.push(catalysts);
+/// Changes the kinetic energy of the particle by `energy`, keeping its direction. A particle at rest is kicked in a
+/// random direction, and one with less kinetic energy than is taken stops.
+fn release_energy(p: &mut Particle, mass: f64, energy: f64, rng: &mut impl Rng) {
+    let kinetic_energy = 0.5 * mass * p.vel.mag_squared();
+    let new_kinetic_energy = (kinetic_energy + energy).max(0.0);
+
+    if kinetic_energy > 0.0 {
+        p.vel *= (new_kinetic_energy / kinetic_energy).sqrt();
+    } else {
+        let angle = rng.gen_range(0.0..TAU);
+        p.vel = Vec2::new(angle.cos(), angle.sin()) * (2.0 * new_kinetic_energy / mass).sqrt();
     }
-
-    (converts_tos, catalysts_of_type)
 }
 
-fn get_colors(count: usize, rng_seed: u64) -> Vec<sdl2::pixels::Color> {
-    let min_delta = 0.8 / (count as f64).sqrt();
-
-    let uv_dist = Uniform::new_inclusive(-1.0, 1.0);
-    let y_dist = Uniform::new_inclusive(0.4, 1.0);
-
-    let mut rng = Xoshiro256PlusPlus::seed_from_u64(rng_seed);
-
-    let mut colors = Vec::with_capacity(count);
-    'outer:
-    loop {
-        let mut yuv = Vec::with_capacity(count);
-
-        for _ in 0..count {
-            let ru: f64 = rng.sample(uv_dist);
-            let rv: f64 = rng.sample(&uv_dist);
-
-            let u = smoothstep_inverse(ru.abs().powf(2.2)) * ru.signum() * 0.436;
-            let v = smoothstep_inverse(rv.abs().powf(2.2)) * rv.signum() * 0.615;
-
-            let y: f64 = rng.sample(&y_dist);
-
-            yuv.push((y, u, v));
-        }
-
-        for i in 0..count {
-            let (y, u, v) = yuv[i];
-
-            for j in (i + 1)..count {
-                let (y2, u2, v2) = yuv[j];
-
-                if (y - y2).powi(2) + (u - u2).powi(2) + (v - v2).powi(2) < min_delta.powi(2) {
-                    continue 'outer;
-                }
-            }
-        }
-
-        for &(y, u, v) in yuv.iter() {
-            let r = y + 1.28033 * v;
-            let g = y + -0.21482 * u + -0.38059 * v;
-            let b = y + 2.12798 * u;
-
-            let r = (r.clamp(0.0, 1.0) * 255.0).round() as u8;
-            let g = (g.clamp(0.0, 1.0) * 255.0).round() as u8;
-            let b = (b.clamp(0.0, 1.0) * 255.0).round() as u8;
-
-            colors.push(sdl2::pixels::Color::RGB(r, g, b));
-        }
+/// The acceleration of the air resistance, quadratic in the speed.
+fn drag(vel: Vec2, resistance: f64) -> Vec2 {
+    let vel_mag = vel.x.hypot(vel.y);
+    Vec2::new(vel.x * vel_mag * -resistance, vel.y * vel_mag * -resistance)
+}
 
-        break;
-    }
+/// Assigns each of `count` cells along an axis to a group, so that cells of the same group are at least `stripe` cells
+/// apart, wrapping around included.
+#[cfg(feature = "parallel")]
+fn stripe_groups(count: usize, stripe: usize) -> Vec<usize> {
+    let striped_count = count / stripe * stripe;
 
-    colors
+    (0..count)
+        .map(|i| if i < striped_count { i % stripe } else { stripe + i - striped_count })
+        .collect()
 }
-
-fn smoothstep_inverse(x: f64) -> f64 {
-    0.5 - ((1.0 - 2.0 * x).asin() / 3.0).sin()
-}
\ No newline at end of file
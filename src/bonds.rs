@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+
+/// When particles of a pair of types bond and how the bond behaves.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct BondRule {
+    /// The type indices of the bonded particles, in any order.
+    pub types: (usize, usize),
+    /// The chance of touching particles bonding in a tick.
+    pub probability: f64,
+    /// The distance the spring pulls or pushes the particles to.
+    pub rest_length: f64,
+    /// The force of the spring per unit of stretch.
+    pub stiffness: f64,
+    /// The force against the particles moving apart or together per unit of relative speed.
+    pub damping: f64,
+    /// The bond breaks when stretched more than this beyond its rest length.
+    pub max_stretch: f64,
+}
+
+impl BondRule {
+    pub fn applies_to(&self, type_a: usize, type_b: usize) -> bool {
+        self.types == (type_a, type_b) || self.types == (type_b, type_a)
+    }
+}
+
+/// A damped spring between two particles, identified by their ids.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Bond {
+    pub a: u64,
+    pub b: u64,
+    /// The index of the rule the bond formed by.
+    pub rule_idx: usize,
+}
@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+
+/// What happens to particles at the edges of the board along one axis.
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize)]
+pub enum Boundary {
+    /// Particles leaving on one side enter on the opposite side and interact across the edge.
+    #[default]
+    Periodic,
+    /// Particles bounce off the edge, keeping `restitution` of their speed.
+    Reflect { restitution: f64 },
+    /// Particles leaving the board are removed.
+    Absorb,
+    /// Particles stop at the edge.
+    Clamp,
+}
+
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize)]
+pub struct Boundaries {
+    pub x: Boundary,
+    pub y: Boundary,
+}
+
+impl Boundary {
+    pub fn is_periodic(self) -> bool {
+        matches!(self, Boundary::Periodic)
+    }
+
+    /// Applies the boundary to a particle that left the board, with `pos` and `vel` along the axis and `pos` relative
+    /// to the edge bounding rect of size `br_size`. Returns false if the particle should be removed.
+    pub fn apply(self, pos: &mut f64, vel: &mut f64, br_size: f64) -> bool {
+        match self {
+            Boundary::Periodic => {},
+            Boundary::Reflect { restitution } => {
+                *pos = if *pos < 0.0 { -*pos } else { 2.0 * br_size - *pos };
+                *pos = pos.clamp(0.0, br_size.next_down());
+                *vel *= -restitution;
+            },
+            Boundary::Absorb => return false,
+            Boundary::Clamp => {
+                *pos = pos.clamp(0.0, br_size.next_down());
+                *vel = 0.0;
+            },
+        }
+
+        true
+    }
+}
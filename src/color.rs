@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub fn new(r: u8, g: u8, b: u8) -> Self {
+        Color { r, g, b }
+    }
+}
+
+#[cfg(feature = "sdl")]
+impl From<Color> for sdl2::pixels::Color {
+    fn from(color: Color) -> Self {
+        sdl2::pixels::Color::RGB(color.r, color.g, color.b)
+    }
+}
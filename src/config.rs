@@ -1,5 +1,18 @@
 use serde::Deserialize;
+use particles::analysis::Score;
+use particles::bonds::BondRule;
+use particles::boundary::Boundaries;
+use particles::dump::DumpFormat;
+use particles::evolution::FitnessKind;
+use particles::field::ExternalField;
+use particles::initial_state::InitialState;
+use particles::integrator::Integrator;
+use particles::reactions::Reaction;
+use particles::rules::TypeSpec;
+use particles::thermostat::Thermostat;
 
+// some fields are only used by the viewer
+#[cfg_attr(not(feature = "sdl"), allow(dead_code))]
 #[derive(Debug, Deserialize)]
 pub struct Config {
     pub image_width: usize,
@@ -9,12 +22,40 @@ pub struct Config {
 
     pub save_frames_to_path: Option<String>,
 
+    pub favourites_path: String,
+
+    pub cursor_radius: f64,
+    pub cursor_acc: f64,
+
+    pub stats_csv_path: Option<String>,
+    pub stats_interval: usize,
+    pub tracking: TrackingConfig,
+
+    pub dump_path: Option<String>,
+    pub dump_interval: usize,
+    pub dump_format: DumpFormat,
+
+    pub search: SearchConfig,
+    pub evolve: EvolveConfig,
+
+    pub headless: bool,
+    pub total_frames: usize,
+
     pub iterations_per_frame: usize,
 
     pub rule_rng_seed: Option<u64>,
     pub generate_chemistry: bool,
+    pub types: Vec<TypeSpec>,
+
+    pub load_rules_from_path: Option<String>,
+    pub save_rules_to_path: Option<String>,
+    pub save_network_to_path: Option<String>,
 
     pub initial_state_rng_seed: Option<u64>,
+    pub initial_state: InitialState,
+
+    pub load_snapshot_from_path: Option<String>,
+    pub save_snapshots_to_path: Option<String>,
 
     pub particle_count: usize,
     pub type_count: usize,
@@ -25,9 +66,74 @@ pub struct Config {
     pub bounding_rect_cols: usize,
     pub bounding_rect_rows: usize,
 
+    pub boundary: Boundaries,
+
+    pub integrator: Integrator,
+    pub dt: f64,
+
     pub touching_pushing_acc: f64,
     pub resistance: f64,
+    pub thermostat: Thermostat,
+    pub external_fields: Vec<ExternalField>,
+    pub obstacles_path: Option<String>,
+    pub bonds: Vec<BondRule>,
+    pub reactions: Vec<Reaction>,
     pub max_field_pulling_acc: f64,
     pub max_field_pushing_acc: f64,
     pub max_radius: f64,
-}
\ No newline at end of file
+}
+
+impl Config {
+    /// Checks the settings that would otherwise only fail in the middle of a run.
+    pub fn validate(&self) -> Result<(), String> {
+        let evolve = &self.evolve;
+        if evolve.population_size == 0 {
+            return Err("evolve.population_size must be at least 1.".to_string());
+        }
+        for (name, value) in [("crossover_rate", evolve.crossover_rate), ("mutation_rate", evolve.mutation_rate)] {
+            if !(0.0..=1.0).contains(&value) {
+                return Err(format!("evolve.{} must be between 0 and 1, got {}.", name, value));
+            }
+        }
+        if evolve.mutation_strength < 0.0 {
+            return Err(format!("evolve.mutation_strength must not be negative, got {}.", evolve.mutation_strength));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchConfig {
+    pub seed_count: usize,
+    pub ticks: usize,
+    pub rank_by: Score,
+    pub persistence_lag: usize,
+    pub cluster_distance: f64,
+    pub min_cluster_size: usize,
+    pub thumbnail_count: usize,
+    pub thumbnail_size: usize,
+    pub output_path: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EvolveConfig {
+    pub generations: usize,
+    pub population_size: usize,
+    pub survivor_count: usize,
+    pub crossover_rate: f64,
+    pub mutation_rate: f64,
+    pub mutation_strength: f64,
+    pub ticks: usize,
+    pub persistence_lag: usize,
+    pub fitness: FitnessKind,
+    pub output_path: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TrackingConfig {
+    pub particle_count: usize,
+    pub interval: usize,
+    pub trail_length: usize,
+    pub msd_csv_path: Option<String>,
+}
@@ -0,0 +1,104 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use serde::{Deserialize, Serialize};
+use crate::board::Board;
+use crate::serialization;
+
+/// The file format of a trajectory dump.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub enum DumpFormat {
+    /// Extended XYZ, read by OVITO and ASE. Species are type indices.
+    ExtendedXyz,
+    /// A LAMMPS text dump, read by OVITO and VMD. LAMMPS types start at 1, so they are type indices plus 1.
+    Lammps,
+}
+
+/// Appends the id, type, board position and velocity of every particle to a file every `interval`-th tick. The
+/// board is a slab in the xy plane at z = 0.
+pub struct DumpWriter {
+    writer: BufWriter<File>,
+    interval: usize,
+    format: DumpFormat,
+}
+
+impl DumpWriter {
+    pub fn create(path: &str, interval: usize, format: DumpFormat) -> Result<Self, String> {
+        serialization::create_parent_dir(path)?;
+        let file = File::create(path).map_err(|e| format!("{}: {}", path, e))?;
+
+        Ok(DumpWriter { writer: BufWriter::new(file), interval: interval.max(1), format })
+    }
+
+    /// Call after every tick. Writes a frame if the tick is due.
+    pub fn record(&mut self, board: &Board) -> Result<(), String> {
+        if !board.tick.is_multiple_of(self.interval) {
+            return Ok(());
+        }
+
+        match self.format {
+            DumpFormat::ExtendedXyz => self.write_extended_xyz(board),
+            DumpFormat::Lammps => self.write_lammps(board),
+        }
+        .map_err(|e| e.to_string())
+    }
+
+    fn write_extended_xyz(&mut self, board: &Board) -> std::io::Result<()> {
+        let pbc = |periodic: bool| if periodic { "T" } else { "F" };
+
+        writeln!(self.writer, "{}", particle_count(board))?;
+        writeln!(
+            self.writer,
+            "Lattice=\"{} 0.0 0.0 0.0 {} 0.0 0.0 0.0 1.0\" Properties=species:S:1:pos:R:3:velo:R:3:id:I:1 Time={} pbc=\"{} {} F\"",
+            board.width,
+            board.height,
+            board.tick,
+            pbc(board.boundary.x.is_periodic()),
+            pbc(board.boundary.y.is_periodic()),
+        )?;
+
+        for br_idx in 0..board.bounding_rects.len() {
+            let origin = board.br_origin(br_idx);
+
+            for p in board.bounding_rects[br_idx].iter() {
+                let pos = origin + p.pos;
+                writeln!(self.writer, "{} {} {} 0.0 {} {} 0.0 {}", p.type_idx, pos.x, pos.y, p.vel.x, p.vel.y, p.id)?;
+            }
+        }
+
+        self.writer.flush()
+    }
+
+    fn write_lammps(&mut self, board: &Board) -> std::io::Result<()> {
+        let bounds = |periodic: bool| if periodic { "pp" } else { "ff" };
+
+        writeln!(self.writer, "ITEM: TIMESTEP")?;
+        writeln!(self.writer, "{}", board.tick)?;
+        writeln!(self.writer, "ITEM: NUMBER OF ATOMS")?;
+        writeln!(self.writer, "{}", particle_count(board))?;
+        writeln!(
+            self.writer,
+            "ITEM: BOX BOUNDS {} {} pp",
+            bounds(board.boundary.x.is_periodic()),
+            bounds(board.boundary.y.is_periodic()),
+        )?;
+        writeln!(self.writer, "0.0 {}", board.width)?;
+        writeln!(self.writer, "0.0 {}", board.height)?;
+        writeln!(self.writer, "-0.5 0.5")?;
+        writeln!(self.writer, "ITEM: ATOMS id type x y z vx vy vz")?;
+
+        for br_idx in 0..board.bounding_rects.len() {
+            let origin = board.br_origin(br_idx);
+
+            for p in board.bounding_rects[br_idx].iter() {
+                let pos = origin + p.pos;
+                writeln!(self.writer, "{} {} {} {} 0.0 {} {} 0.0", p.id, p.type_idx + 1, pos.x, pos.y, p.vel.x, p.vel.y)?;
+            }
+        }
+
+        self.writer.flush()
+    }
+}
+
+fn particle_count(board: &Board) -> usize {
+    board.bounding_rects.iter().map(|br| br.len()).sum()
+}
@@ -0,0 +1,240 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use crate::analysis::{simulate_sampled, Clustering, Score, Scores, VelocitySample};
+use crate::board::Board;
+use crate::particle_type::ConversionType;
+use crate::rules::Rules;
+
+/// How good a board is after a run, higher is better.
+pub trait Fitness {
+    /// `earlier_velocities` were sampled a few ticks before the end of the run, see `analysis::simulate_sampled`.
+    fn evaluate(&self, board: &Board, earlier_velocities: &VelocitySample) -> f64;
+}
+
+/// The built-in fitness functions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum FitnessKind {
+    /// The number of clusters moving coherently at some minimum speed.
+    SelfPropelledClusters { cluster_distance: f64, min_cluster_size: usize, min_coherence: f64, min_speed: f64 },
+    /// The effective number of types among the surviving particles: the exponential of the Shannon entropy of the type
+    /// distribution. Rewards chemistry that keeps many types alive in balanced numbers.
+    TypeDiversity,
+    /// One of the scores used by the rule search.
+    Score { score: Score, cluster_distance: f64, min_cluster_size: usize },
+}
+
+impl Fitness for FitnessKind {
+    fn evaluate(&self, board: &Board, earlier_velocities: &VelocitySample) -> f64 {
+        match *self {
+            FitnessKind::SelfPropelledClusters { cluster_distance, min_cluster_size, min_coherence, min_speed } => {
+                Clustering::measure(board, cluster_distance, min_cluster_size, earlier_velocities)
+                    .clusters
+                    .iter()
+                    .filter(|c| c.coherence() >= min_coherence && c.mean_speed() >= min_speed)
+                    .count() as f64
+            },
+            FitnessKind::TypeDiversity => {
+                let mut type_counts = vec![0; board.particle_types.len()];
+                for p in board.bounding_rects.iter().flatten() {
+                    type_counts[p.type_idx] += 1;
+                }
+
+                let particle_count = type_counts.iter().sum::<usize>().max(1) as f64;
+                let entropy = type_counts
+                    .iter()
+                    .filter(|&&count| count > 0)
+                    .map(|&count| {
+                        let fraction = count as f64 / particle_count;
+                        -fraction * fraction.ln()
+                    })
+                    .sum::<f64>();
+
+                entropy.exp()
+            },
+            FitnessKind::Score { score, cluster_distance, min_cluster_size } => {
+                Scores::measure(board, cluster_distance, min_cluster_size, earlier_velocities).get(score)
+            },
+        }
+    }
+}
+
+/// How strongly rules are changed between generations. The limits are the same as for generating rules.
+#[derive(Debug, Copy, Clone)]
+pub struct Mutation {
+    /// The probability of changing each force curve, catalyst and conversion.
+    pub rate: f64,
+    /// The largest change of a node, as a fraction of the limits.
+    pub strength: f64,
+    pub max_field_pulling_acc: f64,
+    pub max_field_pushing_acc: f64,
+    pub max_radius: f64,
+}
+
+impl Mutation {
+    pub fn mutate(&self, rules: &mut Rules, rng: &mut impl Rng) {
+        let type_count = rules.particle_types.len();
+
+        for (i, pt) in rules.particle_types.iter_mut().enumerate() {
+            for (accelerations, radii) in pt.accelerations_of_pairs.iter_mut().zip(pt.radii_of_pairs.iter_mut()) {
+                if !rng.gen_bool(self.rate) {
+                    continue;
+                }
+
+                // the nodes are changed as (radius, acceleration) pairs, so sorting by radius keeps the curve's shape
+                let mut nodes: Vec<(f64, f64)> = radii.iter().copied().zip(accelerations.iter().copied()).collect();
+
+                // occasionally add or remove a node, keeping between 2 and 5 like generated curves
+                if nodes.len() < 5 && rng.gen_bool(0.1) {
+                    nodes.push((rng.gen_range(0.0..self.max_radius), rng.gen_range(-self.max_field_pushing_acc..self.max_field_pulling_acc)));
+                } else if nodes.len() > 2 && rng.gen_bool(0.1) {
+                    nodes.remove(rng.gen_range(0..nodes.len()));
+                }
+
+                let max_acc_change = self.strength * self.max_field_pulling_acc.max(self.max_field_pushing_acc);
+                let max_radius_change = self.strength * self.max_radius;
+                for (radius, acc) in nodes.iter_mut() {
+                    *radius = (*radius + rng.gen_range(-max_radius_change..=max_radius_change)).clamp(0.0, self.max_radius);
+                    *acc = (*acc + rng.gen_range(-max_acc_change..=max_acc_change))
+                        .clamp(-self.max_field_pushing_acc, self.max_field_pulling_acc);
+                }
+                nodes.sort_unstable_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+                (*radii, *accelerations) = nodes.into_iter().unzip();
+            }
+
+            if rng.gen_bool(self.rate) {
+                let converts_to = rng.gen_range(0..type_count);
+                pt.conversion_type = if converts_to == i {
+                    ConversionType::INERT
+                } else {
+                    let catalysts = match &pt.conversion_type {
+                        ConversionType::CONVERTS { converts_to: _, catalysts } => catalysts.clone(),
+                        ConversionType::INERT => (0..type_count).map(|_| rng.gen_bool(0.5)).collect(),
+                    };
+                    ConversionType::CONVERTS { converts_to, catalysts }
+                };
+            }
+
+            if let ConversionType::CONVERTS { converts_to: _, catalysts } = &mut pt.conversion_type {
+                for catalyst in catalysts.iter_mut() {
+                    if rng.gen_bool(self.rate) {
+                        *catalyst = !*catalyst;
+                    }
+                }
+
+                if !catalysts.contains(&true) {
+                    catalysts[rng.gen_range(0..type_count)] = true;
+                }
+            }
+        }
+    }
+}
+
+/// A rule set in an evolving population.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub id: usize,
+    /// The ids of the candidates it was bred from, none for the initial population.
+    pub parents: (Option<usize>, Option<usize>),
+    pub rules: Rules,
+    /// `None` until the candidate has been run.
+    pub fitness: Option<f64>,
+}
+
+/// How a population of rule sets is evolved.
+#[derive(Debug, Copy, Clone)]
+pub struct Evolution {
+    pub generations: usize,
+    /// The number of fittest candidates kept every generation.
+    pub survivor_count: usize,
+    /// The probability of breeding a child from two parents instead of one.
+    pub crossover_rate: f64,
+    pub mutation: Mutation,
+    /// The number of ticks each candidate is simulated for.
+    pub ticks: usize,
+    /// How many ticks before the end of a run the velocities passed to the fitness are sampled.
+    pub persistence_lag: usize,
+}
+
+impl Evolution {
+    /// Evolves the initial rule sets towards a higher `fitness`. Every generation runs the new candidates on boards
+    /// made by `new_board`, keeps the fittest and fills the population back up with their mutated children.
+    /// `on_generation` is called with the index and the candidates of each generation, fittest first. Returns the
+    /// candidates of the last generation, fittest first.
+    pub fn evolve(
+        &self,
+        fitness: &impl Fitness,
+        initial_rules: Vec<Rules>,
+        mut new_board: impl FnMut(Rules) -> Result<Board, String>,
+        rng: &mut impl Rng,
+        mut on_generation: impl FnMut(usize, &[Candidate]) -> Result<(), String>,
+    ) -> Result<Vec<Candidate>, String> {
+        if initial_rules.is_empty() {
+            return Err("The population is empty.".to_string());
+        }
+
+        let population_size = initial_rules.len();
+        let mut population = initial_rules
+            .into_iter()
+            .enumerate()
+            .map(|(id, rules)| Candidate { id, parents: (None, None), rules, fitness: None })
+            .collect::<Vec<_>>();
+        let mut next_id = population_size;
+
+        for generation in 0..self.generations {
+            for candidate in population.iter_mut().filter(|c| c.fitness.is_none()) {
+                let mut board = new_board(candidate.rules.clone())?;
+                let earlier_velocities = simulate_sampled(&mut board, self.ticks, self.persistence_lag);
+
+                candidate.fitness = Some(fitness.evaluate(&board, &earlier_velocities));
+            }
+
+            population.sort_by(|a, b| b.fitness.unwrap().total_cmp(&a.fitness.unwrap()));
+            on_generation(generation, &population)?;
+
+            if generation + 1 == self.generations {
+                break;
+            }
+
+            let survivor_count = self.survivor_count.clamp(1, population.len());
+            population.truncate(survivor_count);
+
+            while population.len() < population_size {
+                let a = &population[rng.gen_range(0..survivor_count)];
+                let (mut rules, parents) = if rng.gen_bool(self.crossover_rate) {
+                    let b = &population[rng.gen_range(0..survivor_count)];
+                    (crossover(&a.rules, &b.rules, rng), (Some(a.id), Some(b.id)))
+                } else {
+                    (a.rules.clone(), (Some(a.id), None))
+                };
+                self.mutation.mutate(&mut rules, rng);
+
+                population.push(Candidate { id: next_id, parents, rules, fitness: None });
+                next_id += 1;
+            }
+        }
+
+        Ok(population)
+    }
+}
+
+/// Combines two rule sets with the same number of types, taking each force curve and each type's chemistry from either
+/// parent. The colors come from the first parent.
+pub fn crossover(a: &Rules, b: &Rules, rng: &mut impl Rng) -> Rules {
+    let mut child = a.clone();
+
+    for (pt, other_pt) in child.particle_types.iter_mut().zip(b.particle_types.iter()) {
+        for j in 0..pt.radii_of_pairs.len() {
+            if rng.gen_bool(0.5) {
+                pt.radii_of_pairs[j] = other_pt.radii_of_pairs[j].clone();
+                pt.accelerations_of_pairs[j] = other_pt.accelerations_of_pairs[j].clone();
+            }
+        }
+
+        if rng.gen_bool(0.5) {
+            pt.conversion_type = other_pt.conversion_type.clone();
+        }
+    }
+
+    child
+}
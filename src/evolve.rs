@@ -0,0 +1,66 @@
+use std::fs;
+use std::io::Write;
+use rand_xoshiro::rand_core::SeedableRng;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use particles::evolution::Evolution;
+use crate::config::Config;
+use crate::{generate_rules, mutation, new_board};
+
+/// Evolves rule sets, starting from `population_size` consecutive rule seeds starting at `rule_rng_seed`. Every
+/// generation keeps its `survivor_count` fittest candidates and fills the rest of the population with their mutated
+/// children. The lineage and the best rules of each generation are saved to the output directory.
+pub fn run(config: &Config, color_rng_seed: u64, rule_rng_seed: u64, initial_state_rng_seed: u64) -> Result<(), String> {
+    let evolve = &config.evolve;
+
+    let evolution = Evolution {
+        generations: evolve.generations,
+        survivor_count: evolve.survivor_count,
+        crossover_rate: evolve.crossover_rate,
+        mutation: mutation(config),
+        ticks: evolve.ticks,
+        persistence_lag: evolve.persistence_lag,
+    };
+
+    let mut rng = Xoshiro256PlusPlus::seed_from_u64(rule_rng_seed);
+
+    let initial_rules = (0..evolve.population_size)
+        .map(|i| generate_rules(config, color_rng_seed, rule_rng_seed.wrapping_add(i as u64)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    fs::create_dir_all(&evolve.output_path).map_err(|e| e.to_string())?;
+
+    let lineage_path = format!("{}/lineage.csv", evolve.output_path);
+    let mut lineage = fs::File::create(&lineage_path).map_err(|e| format!("{}: {}", lineage_path, e))?;
+    writeln!(lineage, "generation,id,parent_a,parent_b,fitness").map_err(|e| e.to_string())?;
+
+    evolution.evolve(
+        &evolve.fitness,
+        initial_rules,
+        |rules| new_board(config, rules, initial_state_rng_seed),
+        &mut rng,
+        |generation, population| {
+            let id_str = |id: Option<usize>| id.map_or(String::new(), |id| id.to_string());
+            for candidate in population.iter() {
+                writeln!(
+                    lineage,
+                    "{},{},{},{},{}",
+                    generation,
+                    candidate.id,
+                    id_str(candidate.parents.0),
+                    id_str(candidate.parents.1),
+                    candidate.fitness.unwrap(),
+                ).map_err(|e| e.to_string())?;
+            }
+
+            let best = &population[0];
+            best.rules.save(&format!("{}/generation_{:0>3}.ron", evolve.output_path, generation))?;
+            println!("Generation {}: best fitness = {:.4} (candidate {})", generation, best.fitness.unwrap(), best.id);
+
+            Ok(())
+        },
+    )?;
+
+    println!("Saved the lineage to {}", lineage_path);
+
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use std::fs;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::rules::Rules;
+use crate::serialization;
+
+/// A rule set someone liked. Favourite libraries are JSON Lines files, one favourite per line, so they can be appended
+/// to and merged easily.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Favourite {
+    /// The seeds the rules were generated from, None for rules that were mutated or loaded from a file.
+    pub rule_rng_seed: Option<u64>,
+    pub color_rng_seed: Option<u64>,
+    pub rules: Rules,
+}
+
+pub fn append_favourite(path: &str, favourite: &Favourite) -> Result<(), String> {
+    serialization::create_parent_dir(path)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("{}: {}", path, e))?;
+
+    let line = serde_json::to_string(favourite).map_err(|e| e.to_string())?;
+    writeln!(file, "{}", line).map_err(|e| format!("{}: {}", path, e))
+}
+
+/// Reads all favourites of a library, an empty one if the file doesn't exist yet.
+pub fn load_favourites(path: &str) -> Result<Vec<Favourite>, String> {
+    if !Path::new(path).exists() {
+        return Ok(Vec::new());
+    }
+
+    fs::read_to_string(path)
+        .map_err(|e| format!("{}: {}", path, e))?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).map_err(|e| format!("{}: {}", path, e)))
+        .collect()
+}
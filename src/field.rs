@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+use crate::board::Board;
+use crate::vec2;
+use crate::vec2::Vec2;
+
+/// An acceleration applied to particles from outside, independently of their mass.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExternalField {
+    pub kind: FieldKind,
+    /// The type indices the field acts on, or all types if `None`.
+    #[serde(default)]
+    pub types: Option<Vec<usize>>,
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub enum FieldKind {
+    /// The same acceleration everywhere.
+    Gravity { acc: Vec2 },
+    /// Pulls particles towards `center` (pushes them away if `strength` is negative) with an acceleration of
+    /// `strength / (1 + dist)^falloff`.
+    Attractor { center: Vec2, strength: f64, falloff: f64 },
+    /// Accelerates particles counterclockwise around `center` (clockwise if `strength` is negative) with an
+    /// acceleration of `strength / (1 + dist)^falloff`.
+    Vortex { center: Vec2, strength: f64, falloff: f64 },
+    /// Drags particles towards a horizontal flow, whose velocity grows by `rate` per unit of height from zero at the
+    /// middle of the board. `coupling` is how fast particles take on the velocity of the flow.
+    Shear { rate: f64, coupling: f64 },
+}
+
+impl ExternalField {
+    pub fn applies_to(&self, type_idx: usize) -> bool {
+        self.types.as_ref().is_none_or(|types| types.contains(&type_idx))
+    }
+
+    /// The acceleration of a particle at the board position `pos` moving with `vel`.
+    pub fn acc(&self, board: &Board, pos: Vec2, vel: Vec2) -> Vec2 {
+        match self.kind {
+            FieldKind::Gravity { acc } => acc,
+            FieldKind::Attractor { center, strength, falloff } => {
+                let offset = board.offset_between(pos, center);
+                let dist = offset.mag();
+                if dist > 0.0 {
+                    offset / dist * strength / (1.0 + dist).powf(falloff)
+                } else {
+                    vec2::ZERO
+                }
+            },
+            FieldKind::Vortex { center, strength, falloff } => {
+                let offset = board.offset_between(pos, center);
+                let dist = offset.mag();
+                if dist > 0.0 {
+                    // y points down, so this turns counterclockwise on screen
+                    Vec2::new(-offset.y, offset.x) / dist * strength / (1.0 + dist).powf(falloff)
+                } else {
+                    vec2::ZERO
+                }
+            },
+            FieldKind::Shear { rate, coupling } => {
+                let flow = rate * (pos.y - board.height / 2.0);
+                Vec2::new((flow - vel.x) * coupling, 0.0)
+            },
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use particles::board::Board;
+use particles::render;
+use crate::config::Config;
+use crate::{dump_writer, save_mean_squared_displacements, snapshot_path, stats_writer, trajectory_recorder};
+
+pub fn run(mut board: Board, config: &Config) -> Result<(), String> {
+    let mut stats = stats_writer(config, &board, 0)?;
+    let mut dump = dump_writer(config, 0)?;
+    let mut trajectories = trajectory_recorder(config, &board);
+
+    for frame_idx in 0..config.total_frames {
+        for _ in 0..config.iterations_per_frame {
+            board.simulate();
+
+            if let Some(stats) = &mut stats {
+                stats.record(&mut board)?;
+            }
+
+            if let Some(dump) = &mut dump {
+                dump.record(&board)?;
+            }
+
+            if let Some(trajectories) = &mut trajectories {
+                trajectories.record(&board);
+            }
+        }
+
+        if let Some(path) = &config.save_frames_to_path {
+            let mut img_data = render::render(&board, config.image_width, config.image_height);
+            if let Some(trajectories) = &trajectories {
+                render::draw_trails(&mut img_data, &board, trajectories, config.tracking.trail_length, config.image_width, config.image_height);
+            }
+
+            render::save_png(&render::frame_path(path, frame_idx), config.image_width, config.image_height, &img_data)?;
+        }
+    }
+
+    if let Some(path) = &config.save_snapshots_to_path {
+        board.save_snapshot(&snapshot_path(path, config.total_frames))?;
+    }
+
+    save_mean_squared_displacements(config, &trajectories, 0)
+}
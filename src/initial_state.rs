@@ -0,0 +1,300 @@
+use std::f64::consts::TAU;
+use rand::distributions::WeightedIndex;
+use rand::prelude::Distribution;
+use rand::Rng;
+use rand_xoshiro::rand_core::SeedableRng;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use serde::{Deserialize, Serialize};
+use crate::board::Board;
+use crate::render;
+use crate::thermostat::standard_normal;
+use crate::vec2;
+use crate::vec2::Vec2;
+
+/// How particles are placed at the beginning of a simulation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InitialState {
+    pub layout: Layout,
+    /// The relative frequency of each type, or an empty list for equally frequent types. Ignored by `Layout::Image`.
+    pub type_weights: Vec<f64>,
+    pub velocities: InitialVelocities,
+}
+
+/// Where particles are placed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Layout {
+    /// Uniformly random positions.
+    Random,
+    /// Positions and types drawn from a png image stretched over the board. The density of particles is
+    /// proportional to how much of each pixel is covered.
+    Image { path: String, colors: ImageColors },
+    /// A square or hexagonal grid covering the board, spaced so that all particles fit.
+    Lattice { hexagonal: bool },
+    /// Uniformly random positions inside a disk.
+    Disk { center: Vec2, radius: f64 },
+    /// Uniformly random positions inside a ring around `center`, `width` wide.
+    Ring { center: Vec2, radius: f64, width: f64 },
+    /// `count` clusters at random positions, with particles normally distributed around them.
+    Clusters { count: usize, std_dev: f64 },
+    /// One stripe per type across the board, as wide as the type's share of the particles.
+    Stripes { vertical: bool },
+}
+
+/// How the pixels of an initial state image map to particle types.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub enum ImageColors {
+    /// Each pixel becomes the type with the nearest color, or stays empty if black is nearer. The alpha channel is the
+    /// coverage.
+    NearestType,
+    /// The image has indexed colors and each palette index is a type index. Indices past the last type stay empty.
+    PaletteIndex,
+}
+
+/// The velocities particles start with.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub enum InitialVelocities {
+    Zero,
+    /// Normally distributed velocities with a kinetic temperature of `temperature`.
+    Thermal { temperature: f64 },
+    /// A rigid rotation around `center`, counterclockwise if `angular_velocity` is positive.
+    Rotational { center: Vec2, angular_velocity: f64 },
+}
+
+/// Draws the types of particles by their weights.
+enum TypeSampler {
+    Uniform(usize),
+    Weighted(WeightedIndex<f64>),
+}
+
+impl TypeSampler {
+    fn new(type_weights: &[f64], type_count: usize) -> Result<Self, String> {
+        if type_weights.is_empty() {
+            return Ok(TypeSampler::Uniform(type_count));
+        }
+        if type_weights.len() != type_count {
+            return Err(format!("{} type weights are given, but there are {} types.", type_weights.len(), type_count));
+        }
+
+        WeightedIndex::new(type_weights)
+            .map(TypeSampler::Weighted)
+            .map_err(|e| format!("Invalid type weights: {}", e))
+    }
+
+    fn sample(&self, rng: &mut impl Rng) -> usize {
+        match self {
+            TypeSampler::Uniform(type_count) => rng.gen_range(0..*type_count),
+            TypeSampler::Weighted(weighted) => weighted.sample(rng),
+        }
+    }
+
+    /// The fraction of the particles of each type.
+    fn shares(&self, type_weights: &[f64]) -> Vec<f64> {
+        match self {
+            TypeSampler::Uniform(type_count) => vec![1.0 / *type_count as f64; *type_count],
+            TypeSampler::Weighted(_) => {
+                let total = type_weights.iter().sum::<f64>();
+                type_weights.iter().map(|w| w / total).collect()
+            },
+        }
+    }
+}
+
+impl InitialState {
+    /// Places `particle_count` particles on the board.
+    pub fn place(&self, board: &mut Board, particle_count: usize, rng_seed: u64) -> Result<(), String> {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(rng_seed);
+        let types = TypeSampler::new(&self.type_weights, board.particle_types.len())?;
+
+        let positions_and_types = match &self.layout {
+            Layout::Random => (0..particle_count)
+                .map(|_| {
+                    let x = rng.gen_range(0.0..board.width);
+                    let y = rng.gen_range(0.0..board.height);
+                    (Vec2::new(x, y), types.sample(&mut rng))
+                })
+                .collect::<Vec<_>>(),
+            Layout::Image { path, colors } => image_positions_and_types(board, path, *colors, particle_count, &mut rng)?,
+            Layout::Lattice { hexagonal } => lattice_sites(board, particle_count, *hexagonal)
+                .into_iter()
+                .map(|pos| (pos, types.sample(&mut rng)))
+                .collect(),
+            Layout::Disk { center, radius } => (0..particle_count)
+                .map(|_| (*center + random_in_ring(0.0, *radius, &mut rng), types.sample(&mut rng)))
+                .collect(),
+            Layout::Ring { center, radius, width } => (0..particle_count)
+                .map(|_| {
+                    let offset = random_in_ring(radius - width / 2.0, radius + width / 2.0, &mut rng);
+                    (*center + offset, types.sample(&mut rng))
+                })
+                .collect(),
+            Layout::Clusters { count, std_dev } => {
+                if *count == 0 {
+                    return Err("Clusters need a count of at least 1.".to_string());
+                }
+
+                let centers = (0..*count)
+                    .map(|_| Vec2::new(rng.gen_range(0.0..board.width), rng.gen_range(0.0..board.height)))
+                    .collect::<Vec<_>>();
+
+                (0..particle_count)
+                    .map(|_| {
+                        let center = centers[rng.gen_range(0..*count)];
+                        let offset = Vec2::new(standard_normal(&mut rng), standard_normal(&mut rng)) * *std_dev;
+                        (center + offset, types.sample(&mut rng))
+                    })
+                    .collect()
+            },
+            Layout::Stripes { vertical } => {
+                let shares = types.shares(&self.type_weights);
+                let starts = shares
+                    .iter()
+                    .scan(0.0, |start, share| {
+                        *start += share;
+                        Some(*start - share)
+                    })
+                    .collect::<Vec<_>>();
+
+                (0..particle_count)
+                    .map(|_| {
+                        let t = types.sample(&mut rng);
+                        let across = starts[t] + shares[t] * rng.gen::<f64>();
+                        let along = rng.gen::<f64>();
+                        let pos = if *vertical {
+                            Vec2::new(across * board.width, along * board.height)
+                        } else {
+                            Vec2::new(along * board.width, across * board.height)
+                        };
+                        (pos, t)
+                    })
+                    .collect()
+            },
+        };
+
+        for (pos, t) in positions_and_types {
+            let vel = self.velocities.sample(board, pos, t, &mut rng);
+            board.insert_particle(t, pos, vel);
+        }
+
+        Ok(())
+    }
+}
+
+impl InitialVelocities {
+    fn sample(&self, board: &Board, pos: Vec2, type_idx: usize, rng: &mut impl Rng) -> Vec2 {
+        match *self {
+            InitialVelocities::Zero => vec2::ZERO,
+            InitialVelocities::Thermal { temperature } => {
+                let std_dev = (temperature / board.particle_types[type_idx].mass).sqrt();
+                Vec2::new(standard_normal(rng), standard_normal(rng)) * std_dev
+            },
+            InitialVelocities::Rotational { center, angular_velocity } => {
+                // y points down, so this turns counterclockwise on screen
+                let offset = board.offset_between(center, pos);
+                Vec2::new(offset.y, -offset.x) * angular_velocity
+            },
+        }
+    }
+}
+
+/// Places particles of random types at uniformly random positions.
+pub(crate) fn place_random(board: &mut Board, particle_count: usize, rng: &mut impl Rng) {
+    let type_count = board.particle_types.len();
+
+    for _ in 0..particle_count {
+        let x = rng.gen_range(0.0..board.width);
+        let y = rng.gen_range(0.0..board.height);
+        let t = rng.gen_range(0..type_count);
+
+        board.insert_particle(t, Vec2::new(x, y), vec2::ZERO);
+    }
+}
+
+/// A uniformly random offset at a distance between `inner_radius` and `outer_radius`.
+fn random_in_ring(inner_radius: f64, outer_radius: f64, rng: &mut impl Rng) -> Vec2 {
+    let inner_radius = inner_radius.max(0.0);
+    let angle = rng.gen_range(0.0..TAU);
+    let dist = (inner_radius * inner_radius + rng.gen::<f64>() * (outer_radius * outer_radius - inner_radius * inner_radius)).sqrt();
+
+    Vec2::new(angle.cos(), angle.sin()) * dist
+}
+
+/// The first `particle_count` sites of a grid covering the board, row by row. Every other row of a hexagonal grid is
+/// shifted by half a column.
+fn lattice_sites(board: &Board, particle_count: usize, hexagonal: bool) -> Vec<Vec2> {
+    if particle_count == 0 {
+        return Vec::new();
+    }
+
+    let row_spacing_ratio = if hexagonal { 3f64.sqrt() / 2.0 } else { 1.0 };
+    let spacing = (board.width * board.height / (particle_count as f64 * row_spacing_ratio)).sqrt();
+    let cols = ((board.width / spacing).floor() as usize).max(1);
+    let rows = particle_count.div_ceil(cols);
+
+    let col_width = board.width / cols as f64;
+    let row_height = board.height / rows as f64;
+
+    (0..particle_count)
+        .map(|i| {
+            let (col, row) = (i % cols, i / cols);
+            let shift = if hexagonal && row % 2 == 1 { 0.5 } else { 0.0 };
+            Vec2::new((col as f64 + 0.5 + shift) * col_width, (row as f64 + 0.5) * row_height)
+        })
+        .collect()
+}
+
+fn image_positions_and_types(board: &Board, path: &str, colors: ImageColors, particle_count: usize, rng: &mut impl Rng) -> Result<Vec<(Vec2, usize)>, String> {
+    let (image_width, image_height, types_and_coverages) = match colors {
+        ImageColors::NearestType => {
+            let (image_width, image_height, img_data) = render::load_png(path)?;
+            let types_and_coverages = img_data
+                .chunks(4)
+                .map(|px| (nearest_type(board, px), px[3] as f64 / 255.0))
+                .collect::<Vec<_>>();
+
+            (image_width, image_height, types_and_coverages)
+        },
+        ImageColors::PaletteIndex => {
+            let (image_width, image_height, indices) = render::load_png_indices(path)?;
+            let types_and_coverages = indices
+                .iter()
+                .map(|&idx| (Some(idx as usize).filter(|&idx| idx < board.particle_types.len()), 1.0))
+                .collect::<Vec<_>>();
+
+            (image_width, image_height, types_and_coverages)
+        },
+    };
+
+    let weights = types_and_coverages
+        .iter()
+        .map(|&(type_idx, coverage)| if type_idx.is_some() { coverage } else { 0.0 });
+    let pixels = WeightedIndex::new(weights).map_err(|_| format!("{}: no pixel maps to a particle type.", path))?;
+
+    Ok((0..particle_count)
+        .map(|_| {
+            let pixel = pixels.sample(rng);
+            let x = (pixel % image_width) as f64 + rng.gen::<f64>();
+            let y = (pixel / image_width) as f64 + rng.gen::<f64>();
+            let pos = Vec2::new(x * board.width / image_width as f64, y * board.height / image_height as f64);
+
+            (pos, types_and_coverages[pixel].0.unwrap())
+        })
+        .collect())
+}
+
+/// The type with the color nearest to the RGB pixel, or `None` if it's nearest to the black background.
+fn nearest_type(board: &Board, px: &[u8]) -> Option<usize> {
+    let dist_squared = |r: u8, g: u8, b: u8| {
+        [(px[0], r), (px[1], g), (px[2], b)]
+            .iter()
+            .map(|&(a, b)| (a as i32 - b as i32).pow(2))
+            .sum::<i32>()
+    };
+
+    let (type_idx, dist) = board.particle_types
+        .iter()
+        .enumerate()
+        .map(|(i, pt)| (i, dist_squared(pt.color.r, pt.color.g, pt.color.b)))
+        .min_by_key(|&(_, dist)| dist)?;
+
+    (dist < dist_squared(0, 0, 0)).then_some(type_idx)
+}
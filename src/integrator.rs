@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+/// How velocities and positions are advanced by a timestep from the accelerations.
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize)]
+pub enum Integrator {
+    /// Moves particles with their old velocity, then accelerates them. Cheap, but gains energy and is the least stable.
+    Euler,
+    /// Accelerates particles, then moves them with their new velocity. With a timestep of 1 this updates in the
+    /// same order as the original simulation, up to rounding.
+    #[default]
+    SemiImplicitEuler,
+    /// Moves particles with their velocity and half of the last acceleration, then finishes accelerating them with the
+    /// new acceleration. Second order accurate, so it stays stable with larger timesteps.
+    VelocityVerlet,
+}
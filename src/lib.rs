@@ -0,0 +1,23 @@
+pub mod serialization;
+pub mod color;
+pub mod vec2;
+pub mod particle;
+pub mod particle_type;
+pub mod boundary;
+pub mod integrator;
+pub mod thermostat;
+pub mod field;
+pub mod obstacles;
+pub mod bonds;
+pub mod reactions;
+pub mod network;
+pub mod rules;
+pub mod board;
+pub mod initial_state;
+pub mod stats;
+pub mod trajectory;
+pub mod dump;
+pub mod analysis;
+pub mod evolution;
+pub mod favourites;
+pub mod render;
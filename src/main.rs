@@ -1,18 +1,21 @@
 mod config;
-mod vec2;
-mod particle;
-mod particle_type;
-mod board;
-
-use std::{fs, path};
-use std::fs::OpenOptions;
-use std::io::BufWriter;
+mod evolve;
+mod headless;
+mod search;
+#[cfg(feature = "sdl")]
+mod viewer;
+
+use std::{env, fs};
+use std::path::Path;
 use rand::{Rng, thread_rng};
-use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
-use sdl2::gfx::primitives::DrawRenderer;
-use sdl2::rect::Rect;
-use crate::board::Board;
+use particles::board::Board;
+use particles::dump::DumpWriter;
+use particles::evolution::Mutation;
+use particles::network::ReactionNetwork;
+use particles::obstacles::Obstacles;
+use particles::rules::Rules;
+use particles::stats::StatsWriter;
+use particles::trajectory::TrajectoryRecorder;
 use crate::config::Config;
 
 fn main() -> Result<(), String> {
@@ -21,7 +24,14 @@ fn main() -> Result<(), String> {
     // ---------------------------------------------------------------------------------------------
 
     let config_str = fs::read_to_string("config.ron").expect("Error while reading the configuration file.");
-    let config: Config = ron::from_str(&config_str).expect("Error while reading the configuration file.");
+    let mut config: Config = ron::from_str(&config_str).expect("Error while reading the configuration file.");
+    config.validate()?;
+
+    let command = env::args().skip(1).find(|arg| !arg.starts_with("--"));
+
+    if env::args().skip(1).any(|arg| arg == "--headless") {
+        config.headless = true;
+    }
 
     // ---------------------------------------------------------------------------------------------
     // Initialize the simulation state
@@ -54,155 +64,160 @@ fn main() -> Result<(), String> {
         },
     };
 
-    let mut board = Board::new(
-        config.particle_count,
+    match command.as_deref() {
+        Some("search") => return search::run(&config, procedural_color_rng_seed, rule_rng_seed, initial_state_rng_seed),
+        Some("evolve") => return evolve::run(&config, procedural_color_rng_seed, rule_rng_seed, initial_state_rng_seed),
+        Some(command) => return Err(format!("Unknown command `{}`, expected `search` or `evolve`.", command)),
+        None => {},
+    }
+
+    let board = match &config.load_snapshot_from_path {
+        Some(path) => Board::load_snapshot(path)?,
+        None => {
+            let rules = match &config.load_rules_from_path {
+                Some(path) => Rules::load(path)?,
+                None => generate_rules(&config, procedural_color_rng_seed, rule_rng_seed)?,
+            };
+
+            new_board(&config, rules, initial_state_rng_seed)?
+        },
+    };
+
+    if let Some(path) = &config.save_rules_to_path {
+        board.rules().save(path)?;
+    }
+
+    let network = ReactionNetwork::of(&board);
+    if !network.is_empty() {
+        println!("{}", network.summary());
+    }
+    if let Some(path) = &config.save_network_to_path {
+        network.save(path)?;
+    }
+
+    // ---------------------------------------------------------------------------------------------
+    // Run
+    // ---------------------------------------------------------------------------------------------
+
+    if config.headless {
+        return headless::run(board, &config);
+    }
+
+    #[cfg(feature = "sdl")]
+    let rules_are_generated = config.load_snapshot_from_path.is_none() && config.load_rules_from_path.is_none();
+
+    #[cfg(feature = "sdl")]
+    return viewer::run(board, &config, procedural_color_rng_seed, rules_are_generated.then_some(rule_rng_seed), initial_state_rng_seed);
+
+    #[cfg(not(feature = "sdl"))]
+    Err("Built without the `sdl` feature, only headless runs are available. Use `--headless` or set `headless: true`.".to_string())
+}
+
+/// Generates rules from the seeds, replacing their parts written by hand in the config and adding its bonds and
+/// reactions.
+fn generate_rules(config: &Config, color_rng_seed: u64, rule_rng_seed: u64) -> Result<Rules, String> {
+    let mut rules = Rules::generate(
         config.type_count,
-        config.board_width,
-        config.board_height,
-        config.bounding_rect_cols,
-        config.bounding_rect_rows,
-        config.touching_pushing_acc,
-        config.resistance,
         config.max_field_pulling_acc,
         config.max_field_pushing_acc,
         config.max_radius,
         config.generate_chemistry,
-        procedural_color_rng_seed,
+        color_rng_seed,
         rule_rng_seed,
-        initial_state_rng_seed,
-    );
+    ).with_specs(&config.types, rule_rng_seed)?;
 
-    // ---------------------------------------------------------------------------------------------
-    // SDL2 setup
-    // ---------------------------------------------------------------------------------------------
-
-    let sdl_context = sdl2::init()?;
-    let video_subsys = sdl_context.video()?;
-
-    let window = video_subsys
-        .window(
-            "Simulated Annealing Matrix",
-            (config.image_width) as u32,
-            (config.image_height) as u32,
-        )
-        .position_centered()
-        .opengl()
-        .build()
-        .map_err(|e| e.to_string())?;
+    rules.bond_rules = config.bonds.clone();
+    rules.reactions = config.reactions.clone();
+    rules.validate()?;
 
-    let mut canvas = window
-        .into_canvas()
-        .present_vsync()
-        .build()
-        .map_err(|e| e.to_string())?;
+    Ok(rules)
+}
 
-    let mut event_pump = sdl_context.event_pump()?;
+fn new_board(config: &Config, rules: Rules, initial_state_rng_seed: u64) -> Result<Board, String> {
+    let mut board = Board::from_rules(
+        rules,
+        0,
+        config.board_width,
+        config.board_height,
+        config.bounding_rect_cols,
+        config.bounding_rect_rows,
+        config.touching_pushing_acc,
+        config.resistance,
+        initial_state_rng_seed,
+    );
+    config.initial_state.place(&mut board, config.particle_count, initial_state_rng_seed)?;
+    board.boundary = config.boundary;
+    board.integrator = config.integrator;
+    board.dt = config.dt;
+    board.thermostat = config.thermostat;
+    board.external_fields = config.external_fields.clone();
+
+    if let Some(path) = &config.obstacles_path {
+        board.set_obstacles(Some(Obstacles::load_png(path, config.board_width, config.board_height)?));
+    }
 
-    canvas.set_draw_color(sdl2::pixels::Color::RGB(0, 0, 0));
-    canvas.clear();
+    Ok(board)
+}
+
+/// The mutation used to breed new rules.
+fn mutation(config: &Config) -> Mutation {
+    Mutation {
+        rate: config.evolve.mutation_rate,
+        strength: config.evolve.mutation_strength,
+        max_field_pulling_acc: config.max_field_pulling_acc,
+        max_field_pushing_acc: config.max_field_pushing_acc,
+        max_radius: config.max_radius,
+    }
+}
+
+/// The path of a snapshot taken at the `frame_idx`-th frame and saved to the directory `dir`.
+fn snapshot_path(dir: &str, frame_idx: usize) -> String {
+    format!("{}/snapshot_{:0>4}.ron", dir, frame_idx)
+}
+
+/// The path of an output file of the `board_idx`-th board of a run. The first board writes to `path` itself, boards
+/// replacing it in the viewer get a `_1`, `_2`, ... suffix before the extension.
+fn board_path(path: &str, board_idx: usize) -> String {
+    if board_idx == 0 {
+        return path.to_string();
+    }
 
-    // ---------------------------------------------------------------------------------------------
-    // Main loop
-    // ---------------------------------------------------------------------------------------------
+    let path = Path::new(path);
+    let stem = path.file_stem().map_or(String::new(), |stem| stem.to_string_lossy().into_owned());
+    let file_name = match path.extension() {
+        Some(extension) => format!("{}_{}.{}", stem, board_idx, extension.to_string_lossy()),
+        None => format!("{}_{}", stem, board_idx),
+    };
 
-    let mut draw_continuously = true;
-    let mut draw_once = false;
-    let mut simulate_continuously = true;
-    let mut simulate_once = false;
-    let mut running = true;
-    let mut frame_idx: usize = 0;
-    while running {
-        for event in event_pump.poll_iter() {
-            match event {
-                Event::KeyDown {
-                    keycode: Some(Keycode::Space),
-                    ..
-                } => {
-                    draw_continuously = !draw_continuously;
-                    simulate_continuously = !simulate_continuously;
-                },
-                Event::KeyDown {
-                    keycode: Some(Keycode::F),
-                    ..
-                } => {
-                    draw_once = true;
-                    simulate_once = true;
-                },
-                Event::Quit { .. }
-                | Event::KeyDown {
-                    keycode: Some(Keycode::Escape),
-                    ..
-                } => {
-                    running = false;
-                }
-                _ => {}
-            }
-        }
-
-        if simulate_continuously || simulate_once {
-            simulate_once = false;
-
-            for _ in 0..config.iterations_per_frame {
-                board.simulate();
-            }
-        }
-
-        if draw_continuously || draw_once {
-            draw_once = false;
-
-            canvas.set_draw_color(sdl2::pixels::Color::RGB(0, 0, 0));
-            canvas.clear();
-
-            for by in 0..board.br_count_y {
-                let oy = by as f64 * board.br_height;
-
-                for bx in 0..board.br_count_x {
-                    let ox = bx as f64 * board.br_width;
-
-                    let br = &board.bounding_rects[by * board.br_count_x + bx];
-                    for p in br.iter() {
-                        let px = ((p.pos.x + ox) * (config.image_width as f64 / board.width)).round() as i16;
-                        let py = ((p.pos.y + oy) * (config.image_height as f64 / board.height)).round() as i16;
-                        let r = (config.image_height as f64 / board.height / 2.0).round() as i16;
-                        let color = board.particle_types[p.type_idx].color;
-
-                        let _ = canvas.filled_circle(px, py, r, color);
-                    }
-                }
-            }
-
-            match &config.save_frames_to_path {
-                Some(path) => {
-                    let img_data = canvas.read_pixels(Rect::new(0, 0, config.image_width as u32, config.image_height as u32), sdl2::pixels::PixelFormatEnum::RGB888).unwrap();
-
-                    let path_string = format!("{}/frame_{:0>4}.png", path, frame_idx);
-                    let path = path::Path::new(&path_string);
-                    let prefix = path.parent().unwrap();
-                    fs::create_dir_all(prefix).unwrap();
-                    let file = OpenOptions::new()
-                        .write(true)
-                        .create(true)
-                        .open(path)
-                        .unwrap();
-                    let ref mut w = BufWriter::new(file);
-
-                    let mut encoder = png::Encoder::new(w, config.image_width as u32, config.image_height as u32);
-                    encoder.set_color(png::ColorType::Rgb);
-                    encoder.set_depth(png::BitDepth::Eight);
-                    let mut writer = encoder.write_header().unwrap();
-
-                    let img_data = img_data.chunks(4).flat_map(|chunk| [chunk[2], chunk[1], chunk[0]]).collect::<Vec<_>>();
-
-                    writer.write_image_data(&img_data).unwrap();
-                },
-                None => {},
-            }
-
-            frame_idx += 1;
-        }
-
-        canvas.present();
+    path.with_file_name(file_name).to_string_lossy().into_owned()
+}
+
+fn stats_writer(config: &Config, board: &Board, board_idx: usize) -> Result<Option<StatsWriter>, String> {
+    config.stats_csv_path
+        .as_ref()
+        .map(|path| StatsWriter::create(&board_path(path, board_idx), config.stats_interval, board.particle_types.len()))
+        .transpose()
+}
+
+fn dump_writer(config: &Config, board_idx: usize) -> Result<Option<DumpWriter>, String> {
+    config.dump_path
+        .as_ref()
+        .map(|path| DumpWriter::create(&board_path(path, board_idx), config.dump_interval, config.dump_format))
+        .transpose()
+}
+
+/// Follows the particles chosen in the config, if any, starting with their current positions.
+fn trajectory_recorder(config: &Config, board: &Board) -> Option<TrajectoryRecorder> {
+    (config.tracking.particle_count > 0).then(|| {
+        let mut trajectories = TrajectoryRecorder::lowest_ids(board, config.tracking.particle_count, config.tracking.interval);
+        trajectories.record(board);
+        trajectories
+    })
+}
+
+fn save_mean_squared_displacements(config: &Config, trajectories: &Option<TrajectoryRecorder>, board_idx: usize) -> Result<(), String> {
+    match (&config.tracking.msd_csv_path, trajectories) {
+        (Some(path), Some(trajectories)) => trajectories.save_mean_squared_displacements(&board_path(path, board_idx)),
+        _ => Ok(()),
     }
-
-    Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,159 @@
+use std::fs;
+use std::path::Path;
+use serde::Serialize;
+use crate::board::Board;
+use crate::color::Color;
+use crate::particle_type::ConversionType;
+use crate::reactions::Reaction;
+use crate::serialization;
+
+/// The chemistry of a board as a graph: the conversions of single particles and the reactions of pairs.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReactionNetwork {
+    /// The color of each type.
+    pub types: Vec<Color>,
+    pub conversions: Vec<Conversion>,
+    pub reactions: Vec<Reaction>,
+}
+
+/// A type converting to another one when touching any of the catalysts.
+#[derive(Debug, Clone, Serialize)]
+pub struct Conversion {
+    pub from: usize,
+    pub to: usize,
+    pub catalysts: Vec<usize>,
+}
+
+impl ReactionNetwork {
+    pub fn of(board: &Board) -> Self {
+        let types = board.particle_types.iter().map(|pt| pt.color).collect();
+
+        let conversions = board.particle_types
+            .iter()
+            .enumerate()
+            .filter_map(|(from, pt)| match &pt.conversion_type {
+                ConversionType::INERT => None,
+                ConversionType::CONVERTS { converts_to, catalysts } => Some(Conversion {
+                    from,
+                    to: *converts_to,
+                    catalysts: (0..catalysts.len()).filter(|&i| catalysts[i]).collect(),
+                }),
+            })
+            .collect();
+
+        ReactionNetwork { types, conversions, reactions: board.reactions.clone() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.conversions.is_empty() && self.reactions.is_empty()
+    }
+
+    /// The cycles types go through by converting, each starting at its lowest type index. Every type converts to at
+    /// most one other type, so each type is on at most one cycle.
+    pub fn conversion_cycles(&self) -> Vec<Vec<usize>> {
+        let mut converts_to = vec![None; self.types.len()];
+        for conversion in self.conversions.iter() {
+            converts_to[conversion.from] = Some(conversion.to);
+        }
+
+        let mut cycles = Vec::new();
+        for start in 0..self.types.len() {
+            let mut path = vec![start];
+            while let Some(next) = converts_to[*path.last().unwrap()] {
+                if next == start {
+                    if path.iter().all(|&t| t >= start) {
+                        cycles.push(path);
+                    }
+                    break;
+                }
+                if path.contains(&next) {
+                    break;
+                }
+                path.push(next);
+            }
+        }
+
+        cycles
+    }
+
+    /// A few lines describing the cycles, the types that only lead into them, the inert types and the reactions.
+    pub fn summary(&self) -> String {
+        let cycles = self.conversion_cycles();
+        let on_cycle = |t: usize| cycles.iter().any(|cycle| cycle.contains(&t));
+        let arrows = |types: &[usize]| types.iter().map(|t| t.to_string()).collect::<Vec<_>>().join(" -> ");
+
+        let mut lines = vec![format!(
+            "Reaction network: {} types, {} conversions, {} reactions",
+            self.types.len(),
+            self.conversions.len(),
+            self.reactions.len(),
+        )];
+
+        for cycle in cycles.iter() {
+            lines.push(format!("  cycle of {}: {} -> {}", cycle.len(), arrows(cycle), cycle[0]));
+        }
+
+        for conversion in self.conversions.iter().filter(|c| !on_cycle(c.from)) {
+            lines.push(format!("  chain: {}", arrows(&[conversion.from, conversion.to])));
+        }
+
+        let inert = (0..self.types.len())
+            .filter(|&t| !self.conversions.iter().any(|c| c.from == t))
+            .map(|t| t.to_string())
+            .collect::<Vec<_>>();
+        if !inert.is_empty() {
+            lines.push(format!("  inert: {}", inert.join(", ")));
+        }
+
+        for reaction in self.reactions.iter() {
+            let products = reaction.products.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+            let products = if products.is_empty() { "nothing".to_string() } else { products.join(" + ") };
+            lines.push(format!("  reaction: {} + {} -> {}", reaction.reactants.0, reaction.reactants.1, products));
+        }
+
+        lines.join("\n")
+    }
+
+    /// The network as a Graphviz digraph. Types are nodes in their colors, conversions are edges labelled by their
+    /// catalysts, and each reaction is a box with edges from its reactants and to its products.
+    pub fn to_dot(&self) -> String {
+        let mut lines = vec!["digraph reaction_network {".to_string(), "    node [shape=circle, style=filled];".to_string()];
+
+        for (t, color) in self.types.iter().enumerate() {
+            let luma = 0.299 * color.r as f64 + 0.587 * color.g as f64 + 0.114 * color.b as f64;
+            lines.push(format!(
+                "    {} [fillcolor=\"#{:02x}{:02x}{:02x}\", fontcolor=\"{}\"];",
+                t, color.r, color.g, color.b, if luma > 128.0 { "black" } else { "white" },
+            ));
+        }
+
+        for conversion in self.conversions.iter() {
+            let catalysts = conversion.catalysts.iter().map(|t| t.to_string()).collect::<Vec<_>>().join(", ");
+            lines.push(format!("    {} -> {} [label=\"{}\"];", conversion.from, conversion.to, catalysts));
+        }
+
+        for (i, reaction) in self.reactions.iter().enumerate() {
+            lines.push(format!("    r{} [shape=box, style=solid, label=\"rate {}\"];", i, reaction.rate));
+            lines.push(format!("    {} -> r{};", reaction.reactants.0, i));
+            lines.push(format!("    {} -> r{};", reaction.reactants.1, i));
+            for product in reaction.products.iter() {
+                lines.push(format!("    r{} -> {};", i, product));
+            }
+        }
+
+        lines.push("}".to_string());
+        lines.join("\n") + "\n"
+    }
+
+    /// Writes the network to a file. Files ending with `.dot` are written as Graphviz, `.json` as JSON and everything
+    /// else as RON.
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let is_dot = Path::new(path).extension().is_some_and(|ext| ext.eq_ignore_ascii_case("dot"));
+        if !is_dot {
+            return serialization::save(self, path);
+        }
+
+        serialization::create_parent_dir(path)?;
+        fs::write(path, self.to_dot()).map_err(|e| format!("{}: {}", path, e))
+    }
+}
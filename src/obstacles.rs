@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+use crate::render;
+use crate::vec2::Vec2;
+
+/// Impenetrable cells covering the board, one per pixel of the mask image they were loaded from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Obstacles {
+    pub mask_width: usize,
+    pub mask_height: usize,
+    /// Whether each mask pixel is occupied, row by row.
+    pub occupied: Vec<bool>,
+    pub board_width: f64,
+    pub board_height: f64,
+}
+
+impl Obstacles {
+    /// Loads a png mask, scaled to cover the whole board. Bright pixels are obstacles, dark pixels are free.
+    pub fn load_png(path: &str, board_width: f64, board_height: f64) -> Result<Self, String> {
+        let (mask_width, mask_height, img_data) = render::load_png(path)?;
+
+        let occupied = img_data
+            .chunks(4)
+            .map(|px| px[..3].iter().map(|&c| c as u32).sum::<u32>() >= 3 * 128)
+            .collect();
+
+        Ok(Obstacles { mask_width, mask_height, occupied, board_width, board_height })
+    }
+
+    /// Whether the board position is inside an obstacle. Positions outside the board are wrapped around.
+    pub fn contains(&self, pos: Vec2) -> bool {
+        let x = (pos.x.rem_euclid(self.board_width) / self.board_width * self.mask_width as f64) as usize;
+        let y = (pos.y.rem_euclid(self.board_height) / self.board_height * self.mask_height as f64) as usize;
+
+        self.occupied[y.min(self.mask_height - 1) * self.mask_width + x.min(self.mask_width - 1)]
+    }
+}
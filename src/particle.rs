@@ -1,15 +1,30 @@
+use serde::{Deserialize, Serialize};
+use crate::vec2;
 use crate::vec2::Vec2;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct Particle {
+    /// Unique on its board and kept for the whole life of the particle.
+    #[serde(default)]
+    pub id: u64,
     pub type_idx: usize,
     pub can_convert: bool,
     pub pos: Vec2,
     pub vel: Vec2,
+    /// The acceleration from the last interaction of the particles.
+    #[serde(default)]
+    pub acc: Vec2,
+    /// The chance that none of the contacts with catalysts during the last interaction converts the particle.
+    #[serde(default = "default_no_reaction_chance")]
+    pub no_reaction_chance: f64,
+}
+
+fn default_no_reaction_chance() -> f64 {
+    1.0
 }
 
 impl Particle {
-    pub fn new(type_idx: usize, can_convert: bool, pos: Vec2, vel: Vec2) -> Self {
-        Particle { type_idx, can_convert, pos, vel }
+    pub fn new(id: u64, type_idx: usize, can_convert: bool, pos: Vec2, vel: Vec2) -> Self {
+        Particle { id, type_idx, can_convert, pos, vel, acc: vec2::ZERO, no_reaction_chance: default_no_reaction_chance() }
     }
 }
\ No newline at end of file
@@ -1,19 +1,60 @@
-#[derive(Debug, Clone)]
+use serde::{Deserialize, Serialize};
+use crate::color::Color;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ParticleType {
-    pub color: sdl2::pixels::Color,
+    pub color: Color,
     pub accelerations_of_pairs: Vec<Vec<f64>>,
     pub radii_of_pairs: Vec<Vec<f64>>,
     pub conversion_type: ConversionType,
+    /// Scales down both the field and the touching accelerations of the particles of this type.
+    #[serde(default = "default_mass")]
+    pub mass: f64,
+    /// Two particles touch when they are closer than the sum of their radii.
+    #[serde(default = "default_radius")]
+    pub radius: f64,
+    #[serde(default)]
+    pub kinetics: Kinetics,
+}
+
+/// How readily particles of a type convert when touching a catalyst, and the energy the conversion releases.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Kinetics {
+    /// The chance of converting per tick of touching a catalyst.
+    pub rate: f64,
+    /// The kinetic energy a collision with a catalyst needs, measured relative to the pair's center of mass, for the
+    /// conversion to happen at all.
+    pub activation_energy: f64,
+    /// Kinetic energy given to a particle when it converts, or taken from it if negative.
+    pub energy_release: f64,
+}
+
+impl Default for Kinetics {
+    fn default() -> Self {
+        Kinetics { rate: 1.0, activation_energy: 0.0, energy_release: 0.0 }
+    }
+}
+
+impl Kinetics {
+    /// The chance of converting from one contact with a catalyst with the collision energy `impact_energy`.
+    pub fn contact_chance(&self, impact_energy: f64) -> f64 {
+        if impact_energy >= self.activation_energy {
+            self.rate
+        } else {
+            0.0
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ConversionType {
     CONVERTS { converts_to: usize, catalysts: Vec<bool>},
     INERT,
 }
 
 impl ParticleType {
-    pub fn new(color: sdl2::pixels::Color, type_count: usize, max_pulling_acc: f64, max_pushing_acc: f64, max_radius: f64, conversion_type: ConversionType, rng: &mut impl rand::Rng) -> Self {
+    pub fn new(color: Color, type_count: usize, max_pulling_acc: f64, max_pushing_acc: f64, max_radius: f64, conversion_type: ConversionType, rng: &mut impl rand::Rng) -> Self {
         let mut accelerations_of_pairs = Vec::with_capacity(type_count);
         let mut radii_of_pairs = Vec::with_capacity(type_count);
 
@@ -38,6 +79,22 @@ impl ParticleType {
             radii_of_pairs.push(radii);
         }
 
-        ParticleType { color, accelerations_of_pairs, radii_of_pairs, conversion_type, }
+        ParticleType { color, accelerations_of_pairs, radii_of_pairs, conversion_type, mass: default_mass(), radius: default_radius(), kinetics: Kinetics::default() }
     }
-}
\ No newline at end of file
+
+    /// Whether particles of this type can change their type.
+    pub fn can_convert(&self) -> bool {
+        match self.conversion_type {
+            ConversionType::INERT => false,
+            ConversionType::CONVERTS { converts_to: _, catalysts: _ } => true,
+        }
+    }
+}
+
+fn default_mass() -> f64 {
+    1.0
+}
+
+fn default_radius() -> f64 {
+    0.5
+}
@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+
+/// Turns a touching pair of particles into other particles.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reaction {
+    /// The type indices of the reacting particles, in any order.
+    pub reactants: (usize, usize),
+    /// The type indices of the products. The first replaces the first reactant and the second replaces the second
+    /// one, missing products annihilate their reactants and further products are spawned between the pair.
+    pub products: Vec<usize>,
+    /// The chance of reacting per tick of touching.
+    pub rate: f64,
+    /// The kinetic energy the collision needs, measured relative to the pair's center of mass, to react at all.
+    #[serde(default)]
+    pub activation_energy: f64,
+}
+
+impl Reaction {
+    /// Whether the pair of types reacts, and if so, whether they are in the order of the reactants.
+    pub fn matches(&self, type_a: usize, type_b: usize) -> Option<bool> {
+        if self.reactants == (type_a, type_b) {
+            Some(true)
+        } else if self.reactants == (type_b, type_a) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    pub fn validate(&self, type_count: usize) -> Result<(), String> {
+        let (a, b) = self.reactants;
+        if a >= type_count || b >= type_count || self.products.iter().any(|&t| t >= type_count) {
+            return Err(format!("The reaction of {} and {} involves an unknown type.", a, b));
+        }
+
+        Ok(())
+    }
+}
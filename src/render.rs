@@ -0,0 +1,207 @@
+use std::fs::{File, OpenOptions};
+use std::io::BufWriter;
+use std::path;
+use crate::board::Board;
+use crate::color::Color;
+use crate::serialization;
+use crate::trajectory::TrajectoryRecorder;
+use crate::vec2::Vec2;
+
+const OBSTACLE_COLOR: Color = Color { r: 96, g: 96, b: 96 };
+const BOND_COLOR: Color = Color { r: 160, g: 160, b: 160 };
+
+/// Rasterizes the board into an RGB buffer of `image_width` * `image_height` pixels.
+pub fn render(board: &Board, image_width: usize, image_height: usize) -> Vec<u8> {
+    let mut img_data = vec![0; image_width * image_height * 3];
+    draw_obstacles(&mut img_data, board, image_width, image_height);
+
+    let scale_x = image_width as f64 / board.width;
+    let scale_y = image_height as f64 / board.height;
+    for (start, end) in board.bond_segments() {
+        line(&mut img_data, image_width, image_height, start.x * scale_x, start.y * scale_y, end.x * scale_x, end.y * scale_y, BOND_COLOR);
+    }
+
+    for by in 0..board.br_count_y {
+        let oy = by as f64 * board.br_height;
+
+        for bx in 0..board.br_count_x {
+            let ox = bx as f64 * board.br_width;
+
+            let br = &board.bounding_rects[by * board.br_count_x + bx];
+            for p in br.iter() {
+                let px = ((p.pos.x + ox) * (image_width as f64 / board.width)).round() as i64;
+                let py = ((p.pos.y + oy) * (image_height as f64 / board.height)).round() as i64;
+                let r = (board.particle_types[p.type_idx].radius * image_height as f64 / board.height).round() as i64;
+                let color = board.particle_types[p.type_idx].color;
+
+                filled_circle(&mut img_data, image_width, image_height, px, py, r, color);
+            }
+        }
+    }
+
+    img_data
+}
+
+fn filled_circle(img_data: &mut [u8], image_width: usize, image_height: usize, cx: i64, cy: i64, r: i64, color: Color) {
+    for y in (cy - r).max(0)..=(cy + r).min(image_height as i64 - 1) {
+        let dy = y - cy;
+        let half_span = ((r * r - dy * dy) as f64).sqrt().floor() as i64;
+
+        for x in (cx - half_span).max(0)..=(cx + half_span).min(image_width as i64 - 1) {
+            let i = (y as usize * image_width + x as usize) * 3;
+            img_data[i] = color.r;
+            img_data[i + 1] = color.g;
+            img_data[i + 2] = color.b;
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn line(img_data: &mut [u8], image_width: usize, image_height: usize, x0: f64, y0: f64, x1: f64, y1: f64, color: Color) {
+    let steps = (x1 - x0).abs().max((y1 - y0).abs()).ceil().max(1.0) as usize;
+
+    for step in 0..=steps {
+        let t = step as f64 / steps as f64;
+        let x = (x0 + (x1 - x0) * t).round();
+        let y = (y0 + (y1 - y0) * t).round();
+
+        if x >= 0.0 && y >= 0.0 && (x as usize) < image_width && (y as usize) < image_height {
+            let i = (y as usize * image_width + x as usize) * 3;
+            img_data[i..i + 3].copy_from_slice(&[color.r, color.g, color.b]);
+        }
+    }
+}
+
+/// The segments between the last `trail_length` recorded positions of each followed particle, in board coordinates,
+/// with its type color dimmed.
+pub fn trail_segments(board: &Board, trajectories: &TrajectoryRecorder, trail_length: usize) -> Vec<(Vec2, Vec2, Color)> {
+    let locations = board.particle_locations();
+
+    trajectories.trajectories
+        .iter()
+        .filter(|t| t.alive)
+        .filter_map(|t| locations.get(&t.id).map(|&(br_idx, i)| (t, board.bounding_rects[br_idx][i].type_idx)))
+        .flat_map(|(t, type_idx)| {
+            let color = board.particle_types[type_idx].color;
+            let color = Color::new(color.r / 2, color.g / 2, color.b / 2);
+
+            t.trail(trail_length).into_iter().map(move |(start, end)| (start, end, color))
+        })
+        .collect()
+}
+
+/// Draws the last `trail_length` recorded positions of each followed particle as a line in its type color, dimmed.
+pub fn draw_trails(img_data: &mut [u8], board: &Board, trajectories: &TrajectoryRecorder, trail_length: usize, image_width: usize, image_height: usize) {
+    let scale_x = image_width as f64 / board.width;
+    let scale_y = image_height as f64 / board.height;
+
+    for (start, end, color) in trail_segments(board, trajectories, trail_length) {
+        line(img_data, image_width, image_height, start.x * scale_x, start.y * scale_y, end.x * scale_x, end.y * scale_y, color);
+    }
+}
+
+/// Draws the obstacles of the board in gray.
+pub fn draw_obstacles(img_data: &mut [u8], board: &Board, image_width: usize, image_height: usize) {
+    let Some(obstacles) = &board.obstacles else {
+        return;
+    };
+
+    for y in 0..image_height {
+        for x in 0..image_width {
+            let pos = Vec2::new(
+                (x as f64 + 0.5) * board.width / image_width as f64,
+                (y as f64 + 0.5) * board.height / image_height as f64,
+            );
+
+            if obstacles.contains(pos) {
+                let i = (y * image_width + x) * 3;
+                img_data[i..i + 3].copy_from_slice(&[OBSTACLE_COLOR.r, OBSTACLE_COLOR.g, OBSTACLE_COLOR.b]);
+            }
+        }
+    }
+}
+
+/// The path of the `frame_idx`-th frame saved to the directory `dir`.
+pub fn frame_path(dir: &str, frame_idx: usize) -> path::PathBuf {
+    path::Path::new(dir).join(format!("frame_{:0>4}.png", frame_idx))
+}
+
+/// Writes an RGB buffer as a png image, creating the parent directory if needed.
+pub fn save_png(path: &path::Path, image_width: usize, image_height: usize, img_data: &[u8]) -> Result<(), String> {
+    serialization::create_parent_dir(path)?;
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+        .map_err(|e| e.to_string())?;
+    let w = &mut BufWriter::new(file);
+
+    let mut encoder = png::Encoder::new(w, image_width as u32, image_height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+
+    writer.write_image_data(img_data).map_err(|e| e.to_string())
+}
+
+/// Reads a png image of any color type, returning its width, height and RGBA data.
+pub fn load_png(path: &str) -> Result<(usize, usize, Vec<u8>), String> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|e| format!("{}: {}", path, e))?;
+
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).map_err(|e| format!("{}: {}", path, e))?;
+    let (width, height) = (info.width as usize, info.height as usize);
+
+    let channels = match info.color_type {
+        png::ColorType::Grayscale => 1,
+        png::ColorType::GrayscaleAlpha => 2,
+        png::ColorType::Rgb => 3,
+        png::ColorType::Rgba => 4,
+        png::ColorType::Indexed => return Err(format!("{}: unexpected indexed colors.", path)),
+    };
+
+    let img_data = buf
+        .chunks(info.line_size)
+        .take(height)
+        .flat_map(|line| line[..width * channels].chunks(channels))
+        .flat_map(|px| match channels {
+            1 => [px[0], px[0], px[0], 255],
+            2 => [px[0], px[0], px[0], px[1]],
+            3 => [px[0], px[1], px[2], 255],
+            _ => [px[0], px[1], px[2], px[3]],
+        })
+        .collect();
+
+    Ok((width, height, img_data))
+}
+
+/// Reads a png image with indexed colors, returning its width, height and the palette index of each pixel.
+pub fn load_png_indices(path: &str) -> Result<(usize, usize, Vec<u8>), String> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::IDENTITY);
+    let mut reader = decoder.read_info().map_err(|e| format!("{}: {}", path, e))?;
+
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).map_err(|e| format!("{}: {}", path, e))?;
+    let (width, height) = (info.width as usize, info.height as usize);
+
+    if info.color_type != png::ColorType::Indexed {
+        return Err(format!("{}: the image doesn't have indexed colors.", path));
+    }
+
+    // rows are packed with 1, 2, 4 or 8 bits per index, the leftmost pixel in the highest bits
+    let bits = info.bit_depth as usize;
+    let mask = ((1u16 << bits) - 1) as u8;
+    let indices = buf
+        .chunks(info.line_size)
+        .take(height)
+        .flat_map(|line| (0..width).map(move |x| (line[x * bits / 8] >> (8 - bits - x * bits % 8)) & mask))
+        .collect();
+
+    Ok((width, height, indices))
+}
@@ -0,0 +1,347 @@
+use rand::Rng;
+use rand_xoshiro::rand_core::SeedableRng;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use rand::distributions::Uniform;
+use serde::{Deserialize, Serialize};
+use crate::bonds::BondRule;
+use crate::color::Color;
+use crate::particle_type::{ConversionType, Kinetics, ParticleType};
+use crate::reactions::Reaction;
+use crate::serialization;
+
+/// A hand-written particle type. Everything left out is generated randomly.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TypeSpec {
+    pub color: Option<Color>,
+    /// The force curves towards the other types, by type index. Missing curves are generated.
+    pub forces: Vec<Option<ForceCurve>>,
+    /// The type this type converts to, or its own index for an inert type.
+    pub converts_to: Option<usize>,
+    /// The indices of the types catalyzing the conversion.
+    pub catalysts: Option<Vec<usize>>,
+    pub mass: Option<f64>,
+    pub radius: Option<f64>,
+    pub kinetics: Option<Kinetics>,
+}
+
+/// A piecewise linear acceleration over distance. The first acceleration applies at distance 0 and each following one
+/// at the previous radius, so `accelerations[k]` is reached at `radii[k - 1]`. The acceleration falls to 0 at the last
+/// radius and stays 0 past it. Positive accelerations pull, negative ones push.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForceCurve {
+    pub radii: Vec<f64>,
+    pub accelerations: Vec<f64>,
+}
+
+/// Everything that defines how particles behave: the colors, force curves and chemistry of each type, and the bonds
+/// and reactions between pairs of types.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rules {
+    pub particle_types: Vec<ParticleType>,
+    #[serde(default)]
+    pub bond_rules: Vec<BondRule>,
+    #[serde(default)]
+    pub reactions: Vec<Reaction>,
+}
+
+impl Rules {
+    pub fn generate(
+        type_count: usize,
+        max_field_pulling_acc: f64,
+        max_field_pushing_acc: f64,
+        max_radius: f64,
+        generate_chemistry: bool,
+        color_rng_seed: u64,
+        rule_rng_seed: u64,
+    ) -> Self {
+        let mut rule_rng = Xoshiro256PlusPlus::seed_from_u64(rule_rng_seed);
+
+        let (converts_tos, catalysts_of_type) = if generate_chemistry {
+            new_chemistry(type_count, &mut rule_rng)
+        } else {
+            (vec![], vec![])
+        };
+
+        let colors = get_colors(type_count, color_rng_seed);
+
+        let mut particle_types = Vec::with_capacity(type_count);
+        for i in 0..type_count {
+
+            let ct = if generate_chemistry && converts_tos[i] != i {
+                ConversionType::CONVERTS { converts_to: converts_tos[i], catalysts: catalysts_of_type[i].clone() }
+            } else {
+                ConversionType::INERT
+            };
+
+            let color = colors[i];
+
+            let particle_type = ParticleType::new(color, type_count, max_field_pulling_acc, max_field_pushing_acc, max_radius, ct, &mut rule_rng);
+
+            particle_types.push(particle_type)
+        }
+
+        Rules { particle_types, bond_rules: Vec::new(), reactions: Vec::new() }
+    }
+
+    /// Replaces the generated types with the hand-written parts of `specs`, in order. Catalysts of types that are
+    /// made to convert, but weren't generated with any, are drawn from a stream independent of the generation.
+    pub fn with_specs(mut self, specs: &[TypeSpec], rule_rng_seed: u64) -> Result<Self, String> {
+        let type_count = self.particle_types.len();
+        if specs.len() > type_count {
+            return Err(format!("{} types are specified, but type_count is {}.", specs.len(), type_count));
+        }
+
+        let mut rule_rng = Xoshiro256PlusPlus::seed_from_u64(rule_rng_seed);
+        rule_rng.long_jump();
+
+        let is_type = |idx: &usize| *idx < type_count;
+
+        for (i, (pt, spec)) in self.particle_types.iter_mut().zip(specs.iter()).enumerate() {
+            if let Some(color) = spec.color {
+                pt.color = color;
+            }
+            if let Some(mass) = spec.mass {
+                pt.mass = mass;
+            }
+            if let Some(radius) = spec.radius {
+                pt.radius = radius;
+            }
+            if let Some(kinetics) = spec.kinetics {
+                pt.kinetics = kinetics;
+            }
+
+            if spec.forces.len() > type_count {
+                return Err(format!("Type {} has force curves for more than {} types.", i, type_count));
+            }
+            for (j, force) in spec.forces.iter().enumerate() {
+                if let Some(force) = force {
+                    pt.radii_of_pairs[j] = force.radii.clone();
+                    pt.accelerations_of_pairs[j] = force.accelerations.clone();
+                }
+            }
+
+            if !spec.converts_to.iter().all(is_type) || !spec.catalysts.iter().flatten().all(is_type) {
+                return Err(format!("Type {} converts to or is catalyzed by an unknown type.", i));
+            }
+
+            let converts_to = match (spec.converts_to, &pt.conversion_type) {
+                (Some(converts_to), _) => converts_to,
+                (None, ConversionType::CONVERTS { converts_to, catalysts: _ }) => *converts_to,
+                (None, ConversionType::INERT) => i,
+            };
+
+            pt.conversion_type = if converts_to == i {
+                ConversionType::INERT
+            } else {
+                let catalysts = match (&spec.catalysts, &pt.conversion_type) {
+                    (Some(idxs), _) => (0..type_count).map(|j| idxs.contains(&j)).collect(),
+                    (None, ConversionType::CONVERTS { converts_to: _, catalysts }) => catalysts.clone(),
+                    (None, ConversionType::INERT) => new_catalysts(type_count, &mut rule_rng),
+                };
+
+                ConversionType::CONVERTS { converts_to, catalysts }
+            };
+        }
+
+        self.validate()?;
+
+        Ok(self)
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let rules: Rules = serialization::load(path)?;
+        rules.validate()?;
+
+        Ok(rules)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        serialization::save(self, path)
+    }
+
+    /// Checks that the per-pair tables and catalyst masks match the number of types, that masses and radii are
+    /// positive, and that bonds and reactions only involve known types.
+    pub fn validate(&self) -> Result<(), String> {
+        let type_count = self.particle_types.len();
+
+        for (i, pt) in self.particle_types.iter().enumerate() {
+            if pt.mass <= 0.0 || pt.radius <= 0.0 {
+                return Err(format!("Particle type {} has a non-positive mass or radius.", i));
+            }
+
+            if pt.accelerations_of_pairs.len() != type_count || pt.radii_of_pairs.len() != type_count {
+                return Err(format!("Particle type {} doesn't have force curves for all {} types.", i, type_count));
+            }
+
+            for (accelerations, radii) in pt.accelerations_of_pairs.iter().zip(pt.radii_of_pairs.iter()) {
+                if accelerations.len() != radii.len() {
+                    return Err(format!("Particle type {} has a force curve with a different number of accelerations and radii.", i));
+                }
+                if radii.windows(2).any(|w| w[0] > w[1]) {
+                    return Err(format!("Particle type {} has a force curve with unsorted radii.", i));
+                }
+            }
+
+            if let ConversionType::CONVERTS { converts_to, catalysts } = &pt.conversion_type {
+                if *converts_to >= type_count || catalysts.len() != type_count {
+                    return Err(format!("Particle type {} has an invalid conversion.", i));
+                }
+            }
+        }
+
+        for rule in self.bond_rules.iter() {
+            let (a, b) = rule.types;
+            if a >= type_count || b >= type_count {
+                return Err(format!("The bond between {} and {} involves an unknown type.", a, b));
+            }
+        }
+
+        for reaction in self.reactions.iter() {
+            reaction.validate(type_count)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn new_chemistry(type_count: usize, rule_rng: &mut impl Rng) -> (Vec<usize>, Vec<Vec<bool>>) {
+    let mut converts_tos = vec![None; type_count];
+    {
+        let mut i = 0;
+        let mut i2 = 0;
+        let mut self_available = true;
+        let mut blank_types_left = type_count;
+        while i < type_count {
+            let mut until_target = rule_rng.gen_range(if self_available {
+                0..blank_types_left
+            } else {
+                0..(blank_types_left - 1)
+            });
+
+            //println!("{}", until_target);
+
+            if until_target == 0 {
+                converts_tos[i2] = Some(i);
+                //println!("v[{}] = {}", i2, i);
+                while converts_tos.get(i).is_some() && converts_tos[i].is_some() {
+                    i += 1;
+                }
+                i2 = i;
+                self_available = true;
+                blank_types_left -= 1;
+                continue;
+            }
+
+            until_target -= 1;
+
+            let mut target_idx = i + 1;
+            loop {
+                if converts_tos[target_idx].is_none() && target_idx != i2 {
+                    if until_target == 0 {
+                        break;
+                    } else {
+                        until_target -= 1;
+                        target_idx += 1;
+                    }
+                } else {
+                    target_idx += 1;
+                }
+            }
+
+            converts_tos[i2] = Some(target_idx);
+            //println!("v[{}] = {}", i2, target_idx);
+            i2 = target_idx;
+
+            blank_types_left -= 1;
+        }
+    }
+    let converts_tos = converts_tos.iter().map(|v| v.unwrap()).collect::<Vec<_>>();
+
+    let mut catalysts_of_type = Vec::with_capacity(type_count);
+    for (j, &converts_to) in converts_tos.iter().enumerate() {
+        if converts_to == j {
+            catalysts_of_type.push(Vec::new());
+            continue;
+        }
+
+        catalysts_of_type.push(new_catalysts(type_count, rule_rng));
+    }
+
+    (converts_tos, catalysts_of_type)
+}
+
+fn new_catalysts(type_count: usize, rule_rng: &mut impl Rng) -> Vec<bool> {
+    let mut catalysts = vec![false; type_count];
+
+    let mut has_catalyst = false;
+    while !has_catalyst {
+        for catalyst in catalysts.iter_mut() {
+            let val = rule_rng.gen::<bool>();
+            if val {
+                has_catalyst = true;
+            }
+            *catalyst = val;
+        }
+    }
+
+    catalysts
+}
+
+fn get_colors(count: usize, rng_seed: u64) -> Vec<Color> {
+    let min_delta = 0.8 / (count as f64).sqrt();
+
+    let uv_dist = Uniform::new_inclusive(-1.0, 1.0);
+    let y_dist = Uniform::new_inclusive(0.4, 1.0);
+
+    let mut rng = Xoshiro256PlusPlus::seed_from_u64(rng_seed);
+
+    let mut colors = Vec::with_capacity(count);
+    'outer:
+    loop {
+        let mut yuv = Vec::with_capacity(count);
+
+        for _ in 0..count {
+            let ru: f64 = rng.sample(uv_dist);
+            let rv: f64 = rng.sample(uv_dist);
+
+            let u = smoothstep_inverse(ru.abs().powf(2.2)) * ru.signum() * 0.436;
+            let v = smoothstep_inverse(rv.abs().powf(2.2)) * rv.signum() * 0.615;
+
+            let y: f64 = rng.sample(y_dist);
+
+            yuv.push((y, u, v));
+        }
+
+        for i in 0..count {
+            let (y, u, v) = yuv[i];
+
+            for &(y2, u2, v2) in yuv.iter().skip(i + 1) {
+
+                if (y - y2).powi(2) + (u - u2).powi(2) + (v - v2).powi(2) < min_delta.powi(2) {
+                    continue 'outer;
+                }
+            }
+        }
+
+        for &(y, u, v) in yuv.iter() {
+            let r = y + 1.28033 * v;
+            let g = y + -0.21482 * u + -0.38059 * v;
+            let b = y + 2.12798 * u;
+
+            let r = (r.clamp(0.0, 1.0) * 255.0).round() as u8;
+            let g = (g.clamp(0.0, 1.0) * 255.0).round() as u8;
+            let b = (b.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+            colors.push(Color::new(r, g, b));
+        }
+
+        break;
+    }
+
+    colors
+}
+
+fn smoothstep_inverse(x: f64) -> f64 {
+    0.5 - ((1.0 - 2.0 * x).asin() / 3.0).sin()
+}
\ No newline at end of file
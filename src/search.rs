@@ -0,0 +1,78 @@
+use std::fs;
+use std::io::Write;
+use particles::analysis::{simulate_sampled, Scores};
+use particles::render;
+use crate::config::Config;
+use crate::{generate_rules, new_board};
+
+struct SearchResult {
+    rule_rng_seed: u64,
+    scores: Scores,
+    score: f64,
+}
+
+/// Runs `seed_count` consecutive rule seeds starting at `first_rule_rng_seed`, ranks them and saves thumbnails of the
+/// best ones.
+pub fn run(config: &Config, color_rng_seed: u64, first_rule_rng_seed: u64, initial_state_rng_seed: u64) -> Result<(), String> {
+    let search = &config.search;
+
+    let thumbnail_width = search.thumbnail_size;
+    let thumbnail_height = (search.thumbnail_size as f64 * config.board_height / config.board_width).round() as usize;
+
+    let mut results = Vec::with_capacity(search.seed_count);
+    // the best results so far, with their thumbnails
+    let mut best: Vec<(f64, u64, Vec<u8>)> = Vec::with_capacity(search.thumbnail_count + 1);
+
+    for i in 0..search.seed_count {
+        let rule_rng_seed = first_rule_rng_seed.wrapping_add(i as u64);
+
+        let rules = generate_rules(config, color_rng_seed, rule_rng_seed)?;
+        let mut board = new_board(config, rules, initial_state_rng_seed)?;
+
+        let earlier_velocities = simulate_sampled(&mut board, search.ticks, search.persistence_lag);
+
+        let scores = Scores::measure(&board, search.cluster_distance, search.min_cluster_size, &earlier_velocities);
+        let score = scores.get(search.rank_by);
+        println!("{}/{}: rule rng seed = {}, score = {:.4}", i + 1, search.seed_count, rule_rng_seed, score);
+
+        if best.len() < search.thumbnail_count || best.last().is_some_and(|(worst, _, _)| score > *worst) {
+            let thumbnail = render::render(&board, thumbnail_width, thumbnail_height);
+            let idx = best.partition_point(|(other_score, _, _)| *other_score >= score);
+            best.insert(idx, (score, rule_rng_seed, thumbnail));
+            best.truncate(search.thumbnail_count);
+        }
+
+        results.push(SearchResult { rule_rng_seed, scores, score });
+    }
+
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    fs::create_dir_all(&search.output_path).map_err(|e| e.to_string())?;
+
+    let table_path = format!("{}/results.csv", search.output_path);
+    let mut table = fs::File::create(&table_path).map_err(|e| format!("{}: {}", table_path, e))?;
+    writeln!(table, "rank,rule_rng_seed,score,cluster_count,mean_cluster_size,motion_coherence,motion_persistence,type_segregation").map_err(|e| e.to_string())?;
+    for (rank, result) in results.iter().enumerate() {
+        writeln!(
+            table,
+            "{},{},{},{},{},{},{},{}",
+            rank + 1,
+            result.rule_rng_seed,
+            result.score,
+            result.scores.cluster_count,
+            result.scores.mean_cluster_size,
+            result.scores.motion_coherence,
+            result.scores.motion_persistence,
+            result.scores.type_segregation,
+        ).map_err(|e| e.to_string())?;
+    }
+
+    for (rank, (_, rule_rng_seed, thumbnail)) in best.iter().enumerate() {
+        let path = format!("{}/{:0>3}_{}.png", search.output_path, rank + 1, rule_rng_seed);
+        render::save_png(path.as_ref(), thumbnail_width, thumbnail_height, thumbnail)?;
+    }
+
+    println!("Saved the ranking to {}", table_path);
+
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use std::fs;
+use std::path::Path;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Reads a value from a file. Files ending with `.json` are read as JSON, everything else as RON.
+pub fn load<T: DeserializeOwned>(path: &str) -> Result<T, String> {
+    let value_str = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+
+    if is_json(path) {
+        serde_json::from_str(&value_str).map_err(|e| format!("{}: {}", path, e))
+    } else {
+        ron::from_str(&value_str).map_err(|e| format!("{}: {}", path, e))
+    }
+}
+
+/// Writes a value to a file, creating the parent directory if needed. Files ending with `.json` are written as JSON,
+/// everything else as RON.
+pub fn save<T: Serialize>(value: &T, path: &str) -> Result<(), String> {
+    let value_str = if is_json(path) {
+        serde_json::to_string_pretty(value).map_err(|e| e.to_string())?
+    } else {
+        ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default()).map_err(|e| e.to_string())?
+    };
+
+    create_parent_dir(path)?;
+    fs::write(path, value_str).map_err(|e| format!("{}: {}", path, e))
+}
+
+/// Creates the directory a file is going to be written to, if it doesn't exist yet.
+pub fn create_parent_dir(path: impl AsRef<Path>) -> Result<(), String> {
+    match path.as_ref().parent() {
+        Some(prefix) => fs::create_dir_all(prefix).map_err(|e| e.to_string()),
+        None => Ok(()),
+    }
+}
+
+fn is_json(path: &str) -> bool {
+    Path::new(path).extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+}
@@ -0,0 +1,111 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use serde::{Deserialize, Serialize};
+use crate::board::Board;
+use crate::serialization;
+use crate::vec2;
+use crate::vec2::Vec2;
+
+/// Events counted by `Board::simulate`.
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize)]
+pub struct Counters {
+    /// Conversions since the counter was last reset.
+    pub conversions: usize,
+    /// Touching pairs during the last tick.
+    pub touching_pairs: usize,
+    /// Reactions between pairs since the counter was last reset.
+    #[serde(default)]
+    pub reactions: usize,
+}
+
+/// A measurement of the board at one tick.
+#[derive(Debug, Clone)]
+pub struct Stats {
+    pub tick: usize,
+    pub type_counts: Vec<usize>,
+    pub mean_kinetic_energies: Vec<f64>,
+    pub momentum: Vec2,
+    pub conversions: usize,
+    pub touching_pairs: usize,
+    pub temperature: f64,
+    pub reactions: usize,
+}
+
+impl Stats {
+    pub fn measure(board: &Board) -> Self {
+        let type_count = board.particle_types.len();
+
+        let mut type_counts = vec![0; type_count];
+        let mut kinetic_energies = vec![0.0; type_count];
+        let mut momentum = vec2::ZERO;
+
+        for p in board.bounding_rects.iter().flatten() {
+            let mass = board.particle_types[p.type_idx].mass;
+            type_counts[p.type_idx] += 1;
+            kinetic_energies[p.type_idx] += 0.5 * mass * p.vel.mag_squared();
+            momentum += p.vel * mass;
+        }
+
+        let mean_kinetic_energies = kinetic_energies
+            .iter()
+            .zip(type_counts.iter())
+            .map(|(&e, &count)| if count > 0 { e / count as f64 } else { 0.0 })
+            .collect();
+
+        Stats {
+            tick: board.tick,
+            type_counts,
+            mean_kinetic_energies,
+            momentum,
+            conversions: board.counters.conversions,
+            touching_pairs: board.counters.touching_pairs,
+            temperature: board.kinetic_temperature(),
+            reactions: board.counters.reactions,
+        }
+    }
+}
+
+/// Writes the stats of every `interval`-th tick as a row of a CSV file.
+pub struct StatsWriter {
+    writer: BufWriter<File>,
+    interval: usize,
+}
+
+impl StatsWriter {
+    pub fn create(path: &str, interval: usize, type_count: usize) -> Result<Self, String> {
+        serialization::create_parent_dir(path)?;
+        let file = File::create(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut writer = BufWriter::new(file);
+
+        let mut header = vec!["tick".to_string()];
+        header.extend((0..type_count).map(|i| format!("count_{}", i)));
+        header.extend((0..type_count).map(|i| format!("mean_kinetic_energy_{}", i)));
+        header.extend(["momentum_x", "momentum_y", "conversions", "touching_pairs", "temperature", "reactions"].map(String::from));
+        writeln!(writer, "{}", header.join(",")).map_err(|e| e.to_string())?;
+
+        Ok(StatsWriter { writer, interval: interval.max(1) })
+    }
+
+    /// Call after every tick. Writes a row if the tick is due and resets the conversion and reaction counters, so each
+    /// row has the conversions and reactions since the previous one.
+    pub fn record(&mut self, board: &mut Board) -> Result<(), String> {
+        if !board.tick.is_multiple_of(self.interval) {
+            return Ok(());
+        }
+
+        let stats = Stats::measure(board);
+        board.counters.conversions = 0;
+        board.counters.reactions = 0;
+
+        let mut row = vec![stats.tick.to_string()];
+        row.extend(stats.type_counts.iter().map(|count| count.to_string()));
+        row.extend(stats.mean_kinetic_energies.iter().map(|e| e.to_string()));
+        row.push(stats.momentum.x.to_string());
+        row.push(stats.momentum.y.to_string());
+        row.push(stats.conversions.to_string());
+        row.push(stats.touching_pairs.to_string());
+        row.push(stats.temperature.to_string());
+        row.push(stats.reactions.to_string());
+        writeln!(self.writer, "{}", row.join(",")).map_err(|e| e.to_string())
+    }
+}
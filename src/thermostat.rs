@@ -0,0 +1,22 @@
+use std::f64::consts::TAU;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Keeps the kinetic temperature of the board near a target, adding the energy drag removes.
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize)]
+pub enum Thermostat {
+    #[default]
+    None,
+    /// Damps every velocity towards zero with the rate `friction` and kicks it with random noise, so that each
+    /// particle is in contact with a heat bath at `temperature`.
+    Langevin { temperature: f64, friction: f64 },
+    /// Rescales all velocities so that the kinetic temperature relaxes towards `temperature` over `time_constant`.
+    Berendsen { temperature: f64, time_constant: f64 },
+}
+
+/// A sample of the standard normal distribution, by the Box-Muller transform.
+pub(crate) fn standard_normal(rng: &mut impl Rng) -> f64 {
+    let u1: f64 = 1.0 - rng.gen::<f64>();
+    let u2: f64 = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (TAU * u2).cos()
+}
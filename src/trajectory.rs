@@ -0,0 +1,119 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use crate::board::Board;
+use crate::serialization;
+use crate::vec2::Vec2;
+
+/// The recorded positions of one particle.
+#[derive(Debug, Clone)]
+pub struct Trajectory {
+    pub id: u64,
+    pub ticks: Vec<usize>,
+    /// Board positions, wrapped around periodic edges.
+    pub positions: Vec<Vec2>,
+    /// Positions as if the board continued past its periodic edges, so that displacements aren't cut short.
+    pub unwrapped_positions: Vec<Vec2>,
+    /// Whether the particle was still on the board at the last recording.
+    pub alive: bool,
+}
+
+impl Trajectory {
+    /// The segments between the last `length` positions, each starting at a board position. Segments across periodic
+    /// edges end outside the board.
+    pub fn trail(&self, length: usize) -> Vec<(Vec2, Vec2)> {
+        let start = self.positions.len().saturating_sub(length);
+
+        (start + 1..self.positions.len())
+            .map(|k| {
+                let pos = self.positions[k - 1];
+                (pos, pos + self.unwrapped_positions[k] - self.unwrapped_positions[k - 1])
+            })
+            .collect()
+    }
+}
+
+/// Follows chosen particles by their ids, recording their positions every `interval`-th tick.
+#[derive(Debug, Clone)]
+pub struct TrajectoryRecorder {
+    pub trajectories: Vec<Trajectory>,
+    pub interval: usize,
+}
+
+impl TrajectoryRecorder {
+    pub fn new(ids: impl IntoIterator<Item = u64>, interval: usize) -> Self {
+        let trajectories = ids
+            .into_iter()
+            .map(|id| Trajectory { id, ticks: Vec::new(), positions: Vec::new(), unwrapped_positions: Vec::new(), alive: true })
+            .collect();
+
+        TrajectoryRecorder { trajectories, interval }
+    }
+
+    /// Follows the `count` particles with the lowest ids.
+    pub fn lowest_ids(board: &Board, count: usize, interval: usize) -> Self {
+        let mut ids = board.bounding_rects.iter().flatten().map(|p| p.id).collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids.truncate(count);
+
+        Self::new(ids, interval)
+    }
+
+    /// Records the positions at the current tick of the board, if it's a multiple of the interval. Particles that
+    /// have been removed stop being recorded.
+    pub fn record(&mut self, board: &Board) {
+        if !board.tick.is_multiple_of(self.interval.max(1)) {
+            return;
+        }
+
+        let locations = board.particle_locations();
+
+        for trajectory in self.trajectories.iter_mut().filter(|t| t.alive) {
+            let Some(&(br_idx, i)) = locations.get(&trajectory.id) else {
+                trajectory.alive = false;
+                continue;
+            };
+
+            let pos = board.br_origin(br_idx) + board.bounding_rects[br_idx][i].pos;
+            let unwrapped = match (trajectory.positions.last(), trajectory.unwrapped_positions.last()) {
+                (Some(&last), Some(&last_unwrapped)) => last_unwrapped + board.offset_between(last, pos),
+                _ => pos,
+            };
+
+            trajectory.ticks.push(board.tick);
+            trajectory.positions.push(pos);
+            trajectory.unwrapped_positions.push(unwrapped);
+        }
+    }
+
+    /// The mean squared displacement from the first recorded position after each number of recordings, averaged
+    /// over the particles recorded that long, with the number of ticks since the first recording.
+    pub fn mean_squared_displacements(&self) -> Vec<(usize, f64)> {
+        let len = self.trajectories.iter().map(|t| t.unwrapped_positions.len()).max().unwrap_or(0);
+
+        (0..len)
+            .map(|k| {
+                let recorded = self.trajectories.iter().filter(|t| t.unwrapped_positions.len() > k).collect::<Vec<_>>();
+                let sum = recorded
+                    .iter()
+                    .map(|t| (t.unwrapped_positions[k] - t.unwrapped_positions[0]).mag_squared())
+                    .sum::<f64>();
+
+                (recorded[0].ticks[k] - recorded[0].ticks[0], sum / recorded.len() as f64)
+            })
+            .collect()
+    }
+
+    /// Writes the mean squared displacements as a CSV file with `ticks` and `msd` columns.
+    pub fn save_mean_squared_displacements(&self, path: &str) -> Result<(), String> {
+        serialization::create_parent_dir(path)?;
+        let file = File::create(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut writer = BufWriter::new(file);
+
+        writeln!(writer, "ticks,msd").map_err(|e| e.to_string())?;
+        for (ticks, msd) in self.mean_squared_displacements() {
+            writeln!(writer, "{},{}", ticks, msd).map_err(|e| e.to_string())?;
+        }
+
+        writer.flush().map_err(|e| e.to_string())
+    }
+}
@@ -1,8 +1,9 @@
 use std::ops::{Add, Sub, Mul, Div, Neg, AddAssign, SubAssign, MulAssign, DivAssign};
+use serde::{Deserialize, Serialize};
 
 pub const ZERO: Vec2 = Vec2 { x: 0.0, y: 0.0 };
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize)]
 pub struct Vec2 {
     pub x: f64,
     pub y: f64,
@@ -0,0 +1,339 @@
+use std::f64::consts::TAU;
+use rand::{Rng, thread_rng};
+use sdl2::event::Event;
+use sdl2::keyboard::{Keycode, Scancode};
+use sdl2::gfx::primitives::DrawRenderer;
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::rect::Rect;
+use particles::board::Board;
+use particles::dump::DumpWriter;
+use particles::stats::StatsWriter;
+use particles::trajectory::TrajectoryRecorder;
+use particles::render;
+use particles::vec2;
+use particles::vec2::Vec2;
+use crate::config::Config;
+use particles::favourites::{append_favourite, load_favourites, Favourite};
+use crate::{dump_writer, generate_rules, mutation, new_board, save_mean_squared_displacements, snapshot_path, stats_writer, trajectory_recorder};
+
+/// `rule_rng_seed` is the seed the rules were generated from, if they were.
+pub fn run(mut board: Board, config: &Config, color_rng_seed: u64, mut rule_rng_seed: Option<u64>, initial_state_rng_seed: u64) -> Result<(), String> {
+    // ---------------------------------------------------------------------------------------------
+    // SDL2 setup
+    // ---------------------------------------------------------------------------------------------
+
+    let sdl_context = sdl2::init()?;
+    let video_subsys = sdl_context.video()?;
+
+    let window = video_subsys
+        .window(
+            "Simulated Annealing Matrix",
+            (config.image_width) as u32,
+            (config.image_height) as u32,
+        )
+        .position_centered()
+        .opengl()
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let mut canvas = window
+        .into_canvas()
+        .present_vsync()
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let mut event_pump = sdl_context.event_pump()?;
+
+    let mut outputs = Outputs::create(config, &board, 0)?;
+
+    // the obstacles don't move, so they are drawn once into a texture that's copied onto every frame
+    let texture_creator = canvas.texture_creator();
+    let mut obstacle_texture = texture_creator
+        .create_texture_static(PixelFormatEnum::RGB24, config.image_width as u32, config.image_height as u32)
+        .map_err(|e| e.to_string())?;
+    let mut obstacle_img_data = vec![0; config.image_width * config.image_height * 3];
+    render::draw_obstacles(&mut obstacle_img_data, &board, config.image_width, config.image_height);
+    obstacle_texture.update(None, &obstacle_img_data, config.image_width * 3).map_err(|e| e.to_string())?;
+
+    canvas.set_draw_color(sdl2::pixels::Color::RGB(0, 0, 0));
+    canvas.clear();
+
+    // ---------------------------------------------------------------------------------------------
+    // Main loop
+    // ---------------------------------------------------------------------------------------------
+
+    let mut draw_continuously = true;
+    let mut draw_once = false;
+    let mut simulate_continuously = true;
+    let mut simulate_once = false;
+    let mut running = true;
+    let mut frame_idx: usize = 0;
+    let mut selected_type = 0;
+    while running {
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::KeyDown {
+                    keycode: Some(Keycode::Space),
+                    ..
+                } => {
+                    draw_continuously = !draw_continuously;
+                    simulate_continuously = !simulate_continuously;
+                },
+                Event::KeyDown {
+                    keycode: Some(Keycode::F),
+                    ..
+                } => {
+                    draw_once = true;
+                    simulate_once = true;
+                },
+                Event::KeyDown {
+                    keycode: Some(Keycode::S),
+                    ..
+                } => {
+                    match &config.save_snapshots_to_path {
+                        Some(path) => {
+                            let path = snapshot_path(path, frame_idx);
+                            match board.save_snapshot(&path) {
+                                Ok(()) => println!("Saved a snapshot to {}", path),
+                                Err(e) => eprintln!("Could not save a snapshot to {}: {}", path, e),
+                            }
+                        },
+                        None => println!("Set save_snapshots_to_path to save snapshots."),
+                    }
+                },
+                Event::KeyDown {
+                    keycode: Some(Keycode::R),
+                    ..
+                } => {
+                    let seed = thread_rng().gen();
+                    println!("Rule rng seed = {}", seed);
+
+                    match generate_rules(config, color_rng_seed, seed).and_then(|rules| new_board(config, rules, initial_state_rng_seed)) {
+                        Ok(next_board) => {
+                            replace_board(&mut board, next_board, config, &mut outputs, &mut selected_type);
+                            rule_rng_seed = Some(seed);
+                        },
+                        Err(e) => eprintln!("Could not create a board with the new rules: {}", e),
+                    }
+                },
+                Event::KeyDown {
+                    keycode: Some(Keycode::L),
+                    ..
+                } => {
+                    let favourite = Favourite {
+                        rule_rng_seed,
+                        color_rng_seed: rule_rng_seed.map(|_| color_rng_seed),
+                        rules: board.rules(),
+                    };
+                    match append_favourite(&config.favourites_path, &favourite) {
+                        Ok(()) => println!("Added the rules to the favourites in {}", config.favourites_path),
+                        Err(e) => eprintln!("Could not add the rules to the favourites in {}: {}", config.favourites_path, e),
+                    }
+                },
+                Event::KeyDown {
+                    keycode: Some(Keycode::M),
+                    ..
+                } => {
+                    match load_favourites(&config.favourites_path) {
+                        Ok(favourites) if favourites.is_empty() => {
+                            println!("There are no favourites in {} yet, press [L] to add the current rules.", config.favourites_path);
+                        },
+                        Ok(favourites) => {
+                            let idx = thread_rng().gen_range(0..favourites.len());
+                            let mut rules = favourites[idx].rules.clone();
+                            mutation(config).mutate(&mut rules, &mut thread_rng());
+
+                            match new_board(config, rules, initial_state_rng_seed) {
+                                Ok(next_board) => {
+                                    println!("Mutated favourite #{} from {}", idx + 1, config.favourites_path);
+                                    replace_board(&mut board, next_board, config, &mut outputs, &mut selected_type);
+                                    rule_rng_seed = None;
+                                },
+                                Err(e) => eprintln!("Could not create a board with favourite #{}: {}", idx + 1, e),
+                            }
+                        },
+                        Err(e) => eprintln!("Could not load the favourites from {}: {}", config.favourites_path, e),
+                    }
+                },
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    ..
+                } if type_idx_of_key(keycode).is_some_and(|idx| idx < board.particle_types.len()) => {
+                    selected_type = type_idx_of_key(keycode).unwrap();
+                },
+                Event::Quit { .. }
+                | Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    ..
+                } => {
+                    running = false;
+                }
+                _ => {}
+            }
+        }
+
+        // [LMB] - spawn particles of the selected type, [RMB] - erase particles,
+        // [MMB] - pull particles, [Shift] + [MMB] - push particles
+        let mouse = event_pump.mouse_state();
+        let cursor_pos = Vec2::new(
+            mouse.x() as f64 * board.width / config.image_width as f64,
+            mouse.y() as f64 * board.height / config.image_height as f64,
+        );
+        let keyboard = event_pump.keyboard_state();
+        let shift = keyboard.is_scancode_pressed(Scancode::LShift) || keyboard.is_scancode_pressed(Scancode::RShift);
+
+        if mouse.left() {
+            let angle = thread_rng().gen_range(0.0..TAU);
+            let dist = config.cursor_radius * thread_rng().gen::<f64>().sqrt();
+            let pos = cursor_pos + Vec2::new(angle.cos(), angle.sin()) * dist;
+            if !board.is_obstructed(pos) {
+                board.insert_particle(selected_type, pos, vec2::ZERO);
+            }
+        }
+
+        if mouse.right() {
+            board.remove_particles_within(cursor_pos, config.cursor_radius);
+        }
+
+        if simulate_continuously || simulate_once {
+            simulate_once = false;
+
+            for _ in 0..config.iterations_per_frame {
+                if mouse.middle() {
+                    board.apply_radial_acc(cursor_pos, config.cursor_radius, if shift { -config.cursor_acc } else { config.cursor_acc });
+                }
+
+                board.simulate();
+
+                if let Some(stats) = &mut outputs.stats {
+                    stats.record(&mut board)?;
+                }
+
+                if let Some(dump) = &mut outputs.dump {
+                    dump.record(&board)?;
+                }
+
+                if let Some(trajectories) = &mut outputs.trajectories {
+                    trajectories.record(&board);
+                }
+            }
+        }
+
+        if draw_continuously || draw_once {
+            draw_once = false;
+
+            canvas.set_draw_color(sdl2::pixels::Color::RGB(0, 0, 0));
+            canvas.clear();
+            canvas.copy(&obstacle_texture, None, None)?;
+
+            let scale_x = config.image_width as f64 / board.width;
+            let scale_y = config.image_height as f64 / board.height;
+            for (start, end) in board.bond_segments() {
+                let _ = canvas.line(
+                    (start.x * scale_x).round() as i16,
+                    (start.y * scale_y).round() as i16,
+                    (end.x * scale_x).round() as i16,
+                    (end.y * scale_y).round() as i16,
+                    sdl2::pixels::Color::RGB(160, 160, 160),
+                );
+            }
+
+            for (start, end, color) in outputs.trajectories.iter().flat_map(|t| render::trail_segments(&board, t, config.tracking.trail_length)) {
+                let _ = canvas.line(
+                    (start.x * scale_x).round() as i16,
+                    (start.y * scale_y).round() as i16,
+                    (end.x * scale_x).round() as i16,
+                    (end.y * scale_y).round() as i16,
+                    sdl2::pixels::Color::from(color),
+                );
+            }
+
+            for by in 0..board.br_count_y {
+                let oy = by as f64 * board.br_height;
+
+                for bx in 0..board.br_count_x {
+                    let ox = bx as f64 * board.br_width;
+
+                    let br = &board.bounding_rects[by * board.br_count_x + bx];
+                    for p in br.iter() {
+                        let px = ((p.pos.x + ox) * (config.image_width as f64 / board.width)).round() as i16;
+                        let py = ((p.pos.y + oy) * (config.image_height as f64 / board.height)).round() as i16;
+                        let r = (board.particle_types[p.type_idx].radius * config.image_height as f64 / board.height).round() as i16;
+                        let color: sdl2::pixels::Color = board.particle_types[p.type_idx].color.into();
+
+                        let _ = canvas.filled_circle(px, py, r, color);
+                    }
+                }
+            }
+
+            if mouse.left() || mouse.right() || mouse.middle() {
+                let r = (config.cursor_radius * config.image_height as f64 / board.height).round() as i16;
+                let _ = canvas.circle(mouse.x() as i16, mouse.y() as i16, r, sdl2::pixels::Color::RGB(128, 128, 128));
+            }
+
+            if let Some(path) = &config.save_frames_to_path {
+                let img_data = canvas.read_pixels(Rect::new(0, 0, config.image_width as u32, config.image_height as u32), PixelFormatEnum::RGB888)?;
+                let img_data = img_data.chunks(4).flat_map(|chunk| [chunk[2], chunk[1], chunk[0]]).collect::<Vec<_>>();
+
+                render::save_png(&render::frame_path(path, frame_idx), config.image_width, config.image_height, &img_data)?;
+            }
+
+            frame_idx += 1;
+        }
+
+        canvas.present();
+    }
+
+    save_mean_squared_displacements(config, &outputs.trajectories, outputs.board_idx)
+}
+
+/// The files written while a board runs, numbered by the boards replacing each other.
+struct Outputs {
+    board_idx: usize,
+    stats: Option<StatsWriter>,
+    dump: Option<DumpWriter>,
+    trajectories: Option<TrajectoryRecorder>,
+}
+
+impl Outputs {
+    fn create(config: &Config, board: &Board, board_idx: usize) -> Result<Self, String> {
+        Ok(Outputs {
+            board_idx,
+            stats: stats_writer(config, board, board_idx)?,
+            dump: dump_writer(config, board_idx)?,
+            trajectories: trajectory_recorder(config, board),
+        })
+    }
+}
+
+/// Switches to `next_board`, finishing the outputs of the old board and starting new ones, since ticks and particle ids
+/// start over.
+fn replace_board(board: &mut Board, next_board: Board, config: &Config, outputs: &mut Outputs, selected_type: &mut usize) {
+    if let Err(e) = save_mean_squared_displacements(config, &outputs.trajectories, outputs.board_idx) {
+        eprintln!("Could not save the mean squared displacements: {}", e);
+    }
+
+    *board = next_board;
+
+    let board_idx = outputs.board_idx + 1;
+    *outputs = match Outputs::create(config, board, board_idx) {
+        Ok(outputs) => outputs,
+        Err(e) => {
+            eprintln!("Could not restart the outputs: {}", e);
+            Outputs { board_idx, stats: None, dump: None, trajectories: trajectory_recorder(config, board) }
+        },
+    };
+
+    *selected_type = (*selected_type).min(board.particle_types.len().saturating_sub(1));
+}
+
+/// The type index selected by the number keys [1] to [9].
+fn type_idx_of_key(keycode: Keycode) -> Option<usize> {
+    let keys = [
+        Keycode::Num1, Keycode::Num2, Keycode::Num3,
+        Keycode::Num4, Keycode::Num5, Keycode::Num6,
+        Keycode::Num7, Keycode::Num8, Keycode::Num9,
+    ];
+
+    keys.iter().position(|&key| key == keycode)
+}
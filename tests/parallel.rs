@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+use particles::board::Board;
+use particles::boundary::{Boundaries, Boundary};
+use particles::vec2::Vec2;
+
+const TICKS: usize = 100;
+
+/// Serial and parallel interactions sum the accelerations in a different order, so positions drift apart by rounding
+/// errors only.
+const TOLERANCE: f64 = 1e-6;
+
+fn board(boundary: Boundaries) -> Board {
+    let mut board = Board::new(1000, 5, 96.0, 96.0, 6, 6, 0.1, 0.04, 0.001, 0.001, 16.0, true, 0, 0, 0);
+    board.boundary = boundary;
+    board
+}
+
+/// The board position of every particle, by id.
+fn positions(board: &Board) -> HashMap<u64, Vec2> {
+    board.particle_locations()
+        .into_iter()
+        .map(|(id, (br_idx, i))| (id, board.br_origin(br_idx) + board.bounding_rects[br_idx][i].pos))
+        .collect()
+}
+
+fn assert_same_physics(boundary: Boundaries) {
+    let mut serial_board = board(boundary);
+    let mut parallel_board = serial_board.clone();
+    for _ in 0..TICKS {
+        serial_board.simulate_serial();
+        parallel_board.simulate_parallel();
+    }
+
+    let serial_positions = positions(&serial_board);
+    let parallel_positions = positions(&parallel_board);
+    assert_eq!(serial_positions.len(), parallel_positions.len());
+
+    for (id, serial_pos) in serial_positions {
+        let parallel_pos = parallel_positions[&id];
+        let dist = serial_board.offset_between(serial_pos, parallel_pos).mag();
+        assert!(dist < TOLERANCE, "particle {} is {} apart: serial {:?}, parallel {:?}", id, dist, serial_pos, parallel_pos);
+    }
+}
+
+#[test]
+fn parallel_matches_serial_on_periodic_board() {
+    assert_same_physics(Boundaries { x: Boundary::Periodic, y: Boundary::Periodic });
+}
+
+#[test]
+fn parallel_matches_serial_on_bounded_board() {
+    assert_same_physics(Boundaries { x: Boundary::Reflect { restitution: 1.0 }, y: Boundary::Clamp });
+}